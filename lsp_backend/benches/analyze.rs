@@ -0,0 +1,109 @@
+//! 分析流程各阶段的基准：`cargo bench -p lsp_backend`
+
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use ant_lexer::Lexer;
+use ant_parser::Parser;
+use ant_type_checker::TypeChecker;
+use ant_type_checker::table::TypeTable;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use lsp_backend::{LineIndex, check_file};
+use tower_lsp::lsp_types::Position;
+
+#[path = "fixtures/generated.rs"]
+mod generated;
+
+const FIXTURES: &[&str] = &["small", "medium", "large"];
+
+/// `small` 是手写的文件，另外两个按组数生成（约 760 和 7600 行）
+fn fixture(name: &str) -> String {
+    match name {
+        "medium" => generated::source(40),
+        "large" => generated::source(400),
+        _ => std::fs::read_to_string(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("benches/fixtures/{name}.ant")),
+        )
+        .expect("missing benchmark fixture"),
+    }
+}
+
+/// `check_file` 从磁盘读取，生成的内容先写到临时目录
+fn fixture_path(name: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.ant"));
+    std::fs::write(&path, fixture(name)).expect("cannot write benchmark fixture");
+    path
+}
+
+fn phases(c: &mut Criterion) {
+    for name in FIXTURES {
+        let src = fixture(name);
+        let mut group = c.benchmark_group(format!("analyze/{name}"));
+
+        group.bench_function("lexer", |b| {
+            b.iter(|| Lexer::new(black_box(src.clone()), "bench.ant".into()).get_tokens())
+        });
+
+        group.bench_function("parser", |b| {
+            b.iter_batched(
+                || Lexer::new(src.clone(), "bench.ant".into()).get_tokens(),
+                |tokens| Parser::new(tokens).parse_program().is_ok(),
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_function("type_checker", |b| {
+            b.iter_batched(
+                || {
+                    let tokens = Lexer::new(src.clone(), "bench.ant".into()).get_tokens();
+                    let ast = Parser::new(tokens)
+                        .parse_program()
+                        .unwrap_or_else(|_| panic!("fixture `{name}` should parse"));
+                    (ast, Arc::new(Mutex::new(TypeTable::new().init())))
+                },
+                |(ast, table)| TypeChecker::new(table).check_node(ast).is_ok(),
+                BatchSize::SmallInput,
+            )
+        });
+
+        // 包括读取文件和 LSP 侧的补充检查
+        let path = fixture_path(name);
+        group.bench_function("check_file", |b| b.iter(|| check_file(black_box(&path))));
+
+        group.finish();
+    }
+}
+
+fn positions(c: &mut Criterion) {
+    for name in FIXTURES {
+        let src = fixture(name);
+        let mut group = c.benchmark_group(format!("line_index/{name}"));
+
+        group.bench_function("new", |b| b.iter(|| LineIndex::new(black_box(&src))));
+
+        let index = LineIndex::new(&src);
+        let offsets: Vec<usize> = src.char_indices().map(|(i, _)| i).step_by(17).collect();
+        let positions: Vec<Position> = offsets.iter().map(|it| index.position(*it)).collect();
+
+        group.bench_function("offset_to_position", |b| {
+            b.iter(|| {
+                for offset in &offsets {
+                    black_box(index.position(*offset));
+                }
+            })
+        });
+        group.bench_function("position_to_offset", |b| {
+            b.iter(|| {
+                for position in &positions {
+                    black_box(index.offset(*position));
+                }
+            })
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, phases, positions);
+criterion_main!(benches);
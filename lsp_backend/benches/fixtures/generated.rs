@@ -0,0 +1,34 @@
+//! 基准和测试共用的大文件：同一组声明按序号重复，每组 19 行
+
+/// `blocks` 组声明，第 n 组为 `step_n`、`check_n`、`value_n`、`label_n`
+pub fn source(blocks: usize) -> String {
+    (0..blocks)
+        .map(|n| {
+            format!(
+                "\
+/// 第 {n} 组
+func step_{n}(a: i64, b: i64) -> i64 {{
+    let mut acc = a;
+    while acc < b {{
+        acc = acc + {step};
+    }}
+    return acc;
+}}
+
+func check_{n}(x: i64) -> bool {{
+    if x > {n} {{
+        return true;
+    }}
+    return false;
+}}
+
+let value_{n} = step_{n}({n}, {end});
+let label_{n} = \"项目 {n} ✓\";
+
+",
+                step = n % 7 + 1,
+                end = n * 3,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+//! 已关闭文档的缓存：重新打开内容相同的文件时不必重新分析
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use ant_type_checker::table::TypeTable;
+use tower_lsp::lsp_types::{Diagnostic, Url};
+
+use crate::document::Document;
+
+#[derive(Debug)]
+struct Entry {
+    uri: Url,
+    doc: Document,
+    /// 配置变化后作废，重新打开时再分析
+    diagnostics: Option<Vec<Diagnostic>>,
+    /// 关闭时内容对应的类型表，没有成功分析过的话为空
+    table: Option<TypeTable>,
+}
+
+/// 按最近关闭的顺序保存，超出容量时丢弃最早关闭的
+#[derive(Debug, Default)]
+pub struct ClosedDocuments {
+    entries: VecDeque<Entry>,
+}
+
+impl ClosedDocuments {
+    pub fn insert(
+        &mut self,
+        uri: Url,
+        doc: Document,
+        diagnostics: Option<Vec<Diagnostic>>,
+        table: Option<TypeTable>,
+        capacity: usize,
+    ) {
+        self.entries.retain(|it| it.uri != uri);
+        self.entries.push_front(Entry {
+            uri,
+            doc,
+            diagnostics,
+            table,
+        });
+        self.entries.truncate(capacity);
+    }
+
+    /// 取出缓存的文档；文件内容已经变了的话缓存作废
+    pub fn take(
+        &mut self,
+        uri: &Url,
+        text: &str,
+    ) -> Option<(Document, Option<Vec<Diagnostic>>, Option<TypeTable>)> {
+        let idx = self.entries.iter().position(|it| &it.uri == uri)?;
+        let entry = self.entries.remove(idx)?;
+
+        (entry.doc.text == text).then_some((entry.doc, entry.diagnostics, entry.table))
+    }
+
+    pub fn forget_diagnostics(&mut self) {
+        for entry in &mut self.entries {
+            entry.diagnostics = None;
+        }
+    }
+}
+
+/// 每个文件最近一次分析的上游诊断，按文件内容的哈希区分；文档关闭后仍然保留。
+/// 恢复会话时大量文档重新打开，内容没变的不必重新分析
+#[derive(Debug, Default)]
+pub struct AnalysisCache {
+    entries: HashMap<Url, (u64, Vec<Diagnostic>)>,
+}
+
+pub(crate) fn content_hash(text: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl AnalysisCache {
+    pub fn insert(&mut self, uri: Url, text: &str, upstream: Vec<Diagnostic>) {
+        self.entries.insert(uri, (content_hash(text), upstream));
+    }
+
+    /// 内容与分析时不同的话没有缓存
+    pub fn get(&self, uri: &Url, text: &str) -> Option<&[Diagnostic]> {
+        self.entries
+            .get(uri)
+            .filter(|(hash, _)| *hash == content_hash(text))
+            .map(|(_, upstream)| upstream.as_slice())
+    }
+
+    /// import 的文件变了，原来的分析结果不再可信
+    pub fn forget(&mut self, uri: &Url) {
+        self.entries.remove(uri);
+    }
+}
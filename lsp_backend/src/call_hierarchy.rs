@@ -0,0 +1,144 @@
+//! 调用层次（`textDocument/prepareCallHierarchy`、`callHierarchy/*Calls`），只在本文件内查找
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::hover::signature;
+use crate::outline::SymbolKind;
+use crate::references;
+use crate::syntax::{self, TokenKind};
+
+fn item(uri: &Url, doc: &Document, func: usize) -> CallHierarchyItem {
+    let sym = &doc.outline.symbols[func];
+
+    CallHierarchyItem {
+        name: sym.name.clone(),
+        kind: tower_lsp::lsp_types::SymbolKind::FUNCTION,
+        tags: None,
+        detail: Some(signature(&doc.outline, func)),
+        uri: uri.clone(),
+        range: doc.lines.range(sym.span),
+        selection_range: doc.lines.range(sym.name_span),
+        data: None,
+    }
+}
+
+/// 条目对应的函数声明：按名字的位置找回
+fn function_of(doc: &Document, item: &CallHierarchyItem) -> Option<usize> {
+    let offset = doc.lines.offset(item.selection_range.start);
+    doc.outline
+        .symbols
+        .iter()
+        .position(|s| s.kind == SymbolKind::Function && s.name_span.0 == offset)
+}
+
+/// 光标下的函数名对应的所有声明：同一作用域中重复声明的同名函数都返回，由用户选择
+pub(crate) fn prepare(uri: &Url, doc: &Document, pos: Position) -> Option<Vec<CallHierarchyItem>> {
+    let outline = &doc.outline;
+    let decl = references::definition_at(&doc.text, &doc.tokens, outline, doc.lines.offset(pos))?;
+    if outline.symbols[decl].kind != SymbolKind::Function {
+        return None;
+    }
+
+    let items: Vec<_> = outline
+        .overloads(decl)
+        .into_iter()
+        .map(|func| item(uri, doc, func))
+        .collect();
+    Some(items)
+}
+
+/// 函数体中的调用：`(调用处名字的字节范围, 被调用的函数)`
+fn calls_in(doc: &Document, span: (usize, usize)) -> Vec<((usize, usize), usize)> {
+    let (src, outline) = (doc.text.as_str(), &doc.outline);
+    let tokens = syntax::code_tokens(&doc.tokens);
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.kind == TokenKind::Ident && span.0 <= t.start && t.end <= span.1)
+        .filter(|(i, _)| tokens.get(i + 1).is_some_and(|t| t.is(src, "(")))
+        .filter(|(i, _)| !references::is_member(src, &tokens, *i))
+        .filter(|(i, _)| !i.checked_sub(1).is_some_and(|p| tokens[p].is(src, "func")))
+        .filter_map(|(_, t)| {
+            let callee = outline.resolve_at(t.text(src), t.start)?;
+            (outline.symbols[callee].kind == SymbolKind::Function)
+                .then_some(((t.start, t.end), callee))
+        })
+        .collect()
+}
+
+/// 按函数分组，组内按位置排序
+fn grouped(calls: Vec<(usize, (usize, usize))>) -> Vec<(usize, Vec<(usize, usize)>)> {
+    let mut groups: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+    for (func, span) in calls {
+        groups.entry(func).or_default().push(span);
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by_key(|(func, _)| *func);
+    groups
+}
+
+/// 调用了条目函数的函数；不在任何函数中的调用（文件顶层）不列出
+pub(crate) fn incoming(
+    uri: &Url,
+    doc: &Document,
+    target: &CallHierarchyItem,
+) -> Option<Vec<CallHierarchyIncomingCall>> {
+    let outline = &doc.outline;
+    let target = function_of(doc, target)?;
+
+    let calls = outline
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.kind == SymbolKind::Function)
+        .flat_map(|(caller, s)| {
+            calls_in(doc, s.span)
+                .into_iter()
+                .filter(|(_, callee)| *callee == target)
+                // 嵌套函数中的调用只算在最内层函数上
+                .filter(move |(span, _)| outline.enclosing_function(span.0) == Some(caller))
+                .map(move |(span, _)| (caller, span))
+        })
+        .collect();
+
+    Some(
+        grouped(calls)
+            .into_iter()
+            .map(|(caller, spans)| CallHierarchyIncomingCall {
+                from: item(uri, doc, caller),
+                from_ranges: spans.into_iter().map(|it| doc.lines.range(it)).collect(),
+            })
+            .collect(),
+    )
+}
+
+/// 条目函数中调用的函数；嵌套函数中的调用不算，与 `incoming` 一致
+pub(crate) fn outgoing(
+    uri: &Url,
+    doc: &Document,
+    source: &CallHierarchyItem,
+) -> Option<Vec<CallHierarchyOutgoingCall>> {
+    let outline = &doc.outline;
+    let func = function_of(doc, source)?;
+
+    let calls = calls_in(doc, outline.symbols[func].span)
+        .into_iter()
+        .filter(|(span, _)| outline.enclosing_function(span.0) == Some(func))
+        .map(|(span, callee)| (callee, span))
+        .collect();
+
+    Some(
+        grouped(calls)
+            .into_iter()
+            .map(|(callee, spans)| CallHierarchyOutgoingCall {
+                to: item(uri, doc, callee),
+                from_ranges: spans.into_iter().map(|it| doc.lines.range(it)).collect(),
+            })
+            .collect(),
+    )
+}
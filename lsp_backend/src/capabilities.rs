@@ -0,0 +1,124 @@
+//! 按客户端声明的能力决定要提供的功能
+
+use tower_lsp::lsp_types::*;
+
+use crate::custom;
+use crate::semantic;
+
+/// 只提供客户端会用到的功能；文本同步总是需要
+pub(crate) fn negotiate(client: &ClientCapabilities) -> ServerCapabilities {
+    let text = client.text_document.as_ref();
+    let supports = |f: fn(&TextDocumentClientCapabilities) -> bool| text.is_some_and(f);
+
+    let execute_command = client
+        .workspace
+        .as_ref()
+        .is_some_and(|it| it.execute_command.is_some());
+
+    let workspace_symbol = client
+        .workspace
+        .as_ref()
+        .is_some_and(|it| it.symbol.is_some());
+
+    let workspace_folders = client
+        .workspace
+        .as_ref()
+        .and_then(|it| it.workspace_folders)
+        .unwrap_or(false);
+
+    ServerCapabilities {
+        position_encoding: Some(position_encoding(client)),
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        completion_provider: supports(|it| it.completion.is_some()).then(|| CompletionOptions {
+            trigger_characters: Some(vec!["_".into(), ":".into(), ".".into()]),
+            resolve_provider: Some(true),
+            ..Default::default()
+        }),
+        hover_provider: supports(|it| it.hover.is_some())
+            .then_some(HoverProviderCapability::Simple(true)),
+        execute_command_provider: execute_command.then(|| ExecuteCommandOptions {
+            commands: vec![
+                custom::TOGGLE_DIAGNOSTICS.into(),
+                custom::NEXT_DIAGNOSTIC.into(),
+                custom::PREV_DIAGNOSTIC.into(),
+                custom::FIX_ALL.into(),
+            ],
+            ..Default::default()
+        }),
+        document_symbol_provider: supports(|it| it.document_symbol.is_some())
+            .then_some(OneOf::Left(true)),
+        workspace_symbol_provider: workspace_symbol.then_some(OneOf::Left(true)),
+        document_link_provider: supports(|it| it.document_link.is_some()).then(|| {
+            DocumentLinkOptions {
+                resolve_provider: Some(true),
+                work_done_progress_options: Default::default(),
+            }
+        }),
+        code_action_provider: supports(|it| it.code_action.is_some())
+            .then_some(CodeActionProviderCapability::Simple(true)),
+        linked_editing_range_provider: supports(|it| it.linked_editing_range.is_some())
+            .then_some(LinkedEditingRangeServerCapabilities::Simple(true)),
+        call_hierarchy_provider: supports(|it| it.call_hierarchy.is_some())
+            .then_some(CallHierarchyServerCapability::Simple(true)),
+        inlay_hint_provider: supports(|it| it.inlay_hint.is_some()).then_some(OneOf::Left(true)),
+        folding_range_provider: supports(|it| it.folding_range.is_some())
+            .then_some(FoldingRangeProviderCapability::Simple(true)),
+        inline_value_provider: supports(|it| it.inline_value.is_some())
+            .then_some(OneOf::Left(true)),
+        moniker_provider: supports(|it| it.moniker.is_some()).then_some(OneOf::Left(true)),
+        semantic_tokens_provider: supports(|it| it.semantic_tokens.is_some()).then(|| {
+            SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                legend: semantic::legend(),
+                range: Some(true),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                work_done_progress_options: Default::default(),
+            })
+        }),
+        workspace: workspace_folders.then(|| WorkspaceServerCapabilities {
+            workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                supported: Some(true),
+                change_notifications: Some(OneOf::Left(true)),
+            }),
+            file_operations: None,
+        }),
+        ..Default::default()
+    }
+}
+
+/// 位置编码：客户端列出的编码中第一个支持的，没有列出时按协议默认用 UTF-16
+pub(crate) fn position_encoding(client: &ClientCapabilities) -> PositionEncodingKind {
+    let supported = [
+        PositionEncodingKind::UTF8,
+        PositionEncodingKind::UTF16,
+        PositionEncodingKind::UTF32,
+    ];
+
+    client
+        .general
+        .as_ref()
+        .and_then(|it| it.position_encodings.as_ref())
+        .and_then(|it| it.iter().find(|kind| supported.contains(kind)))
+        .cloned()
+        .unwrap_or(PositionEncodingKind::UTF16)
+}
+
+/// 悬停内容的格式：客户端明确列出且不含 Markdown 时用纯文本
+pub(crate) fn hover_markup(client: &ClientCapabilities) -> MarkupKind {
+    let formats = client
+        .text_document
+        .as_ref()
+        .and_then(|it| it.hover.as_ref())
+        .and_then(|it| it.content_format.as_ref());
+
+    match formats {
+        Some(formats) if !formats.contains(&MarkupKind::Markdown) => MarkupKind::PlainText,
+        _ => MarkupKind::Markdown,
+    }
+}
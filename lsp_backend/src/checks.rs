@@ -0,0 +1,1894 @@
+//! LSP 侧的补充检查：在上游类型检查之外，给出更具体、可定位的诊断
+
+use ant_type_checker::table::TypeTable;
+use serde_json::json;
+use tower_lsp::lsp_types::*;
+
+use crate::TYPE_ERROR;
+use crate::config::DiagnosticsSettings;
+use crate::document::Document;
+use crate::outline::{self, Outline, SymbolKind};
+use crate::references;
+use crate::style;
+use crate::symbols;
+use crate::syntax::{SyntaxToken, TokenKind, is_ident_char};
+
+pub(crate) const ARITY_MISMATCH: &str = "arity-mismatch";
+pub(crate) const NOT_CALLABLE: &str = "not-callable";
+pub(crate) const UNREACHABLE_CODE: &str = "unreachable-code";
+pub(crate) const CONSTANT_CONDITION: &str = "constant-condition";
+pub(crate) const BRANCH_TYPE_MISMATCH: &str = "branch-type-mismatch";
+pub(crate) const USE_BEFORE_DECLARATION: &str = "use-before-declaration";
+pub(crate) const MISSING_RETURN: &str = "missing-return";
+pub(crate) const EMPTY_FUNCTION_BODY: &str = "empty-function-body";
+pub(crate) const RETURN_TYPE_MISMATCH: &str = "return-type-mismatch";
+pub(crate) const UNEXPECTED_RETURN_VALUE: &str = "unexpected-return-value";
+pub(crate) const DIVISION_BY_ZERO: &str = "division-by-zero";
+pub(crate) const ASSIGNMENT_IN_CONDITION: &str = "assignment-in-condition";
+pub(crate) const INTEGER_OVERFLOW: &str = "integer-overflow";
+pub(crate) const DUPLICATE_PARAMETER: &str = "duplicate-parameter";
+pub(crate) const IMMUTABLE_ASSIGNMENT: &str = "immutable-assignment";
+pub(crate) const INCOMPATIBLE_COMPARISON: &str = "incompatible-comparison";
+pub(crate) const TODO_COMMENT: &str = "todo-comment";
+pub(crate) const RECURSIVE_TYPE: &str = "recursive-type";
+pub(crate) const UNKNOWN_FIELD: &str = "unknown-field";
+pub(crate) const MISSING_FIELDS: &str = "missing-fields";
+pub(crate) const NO_SUCH_FIELD: &str = "no-such-field";
+
+pub(crate) struct CheckContext<'a> {
+    pub doc: &'a Document,
+    pub uri: &'a Url,
+    /// 诊断的 `source`
+    pub source: &'a str,
+    /// 不含注释的 token
+    pub tokens: &'a [SyntaxToken],
+    pub outline: &'a Outline,
+    pub settings: &'a DiagnosticsSettings,
+    /// 上游分析成功时得到的类型表；没有时需要推断类型的检查跳过
+    pub table: Option<&'a TypeTable>,
+}
+
+impl CheckContext<'_> {
+    pub(crate) fn src(&self) -> &str {
+        &self.doc.text
+    }
+
+    pub(crate) fn diagnostic(
+        &self,
+        span: (usize, usize),
+        code: &str,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            range: self.doc.lines.range(span),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(code.into())),
+            source: Some(self.source.to_string()),
+            message,
+            ..Default::default()
+        }
+    }
+
+    fn related(&self, span: (usize, usize), message: String) -> DiagnosticRelatedInformation {
+        DiagnosticRelatedInformation {
+            location: Location::new(self.uri.clone(), self.doc.lines.range(span)),
+            message,
+        }
+    }
+}
+
+pub(crate) fn run(ctx: &CheckContext) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    arity(ctx, &mut out);
+    not_callable(ctx, &mut out);
+    branch_types(ctx, &mut out);
+    use_before_declaration(ctx, &mut out);
+    missing_return(ctx, &mut out);
+    return_types(ctx, &mut out);
+    division_by_zero(ctx, &mut out);
+    assignment_in_condition(ctx, &mut out);
+    integer_overflow(ctx, &mut out);
+    duplicate_params(ctx, &mut out);
+    immutable_assignment(ctx, &mut out);
+    incompatible_comparison(ctx, &mut out);
+    recursive_types(ctx, &mut out);
+    struct_literals(ctx, &mut out);
+    field_access(ctx, &mut out);
+    style::run(ctx, &mut out);
+    if ctx.settings.unreachable_code {
+        unreachable(ctx, &mut out);
+    }
+    if ctx.settings.constant_conditions {
+        constant_conditions(ctx, &mut out);
+    }
+    if ctx.settings.todo.enabled {
+        todo_comments(ctx, &mut out);
+    }
+    out
+}
+
+/// 补充诊断覆盖的范围：`data.covers` 中记录的整个表达式，没有时是诊断本身的范围
+fn covered_range(diag: &Diagnostic) -> Range {
+    diag.data
+        .as_ref()
+        .and_then(|it| it.get("covers"))
+        .and_then(|it| serde_json::from_value(it.clone()).ok())
+        .unwrap_or(diag.range)
+}
+
+/// 合并上游诊断与补充检查：补充的错误更具体，覆盖落在同一调用范围内的上游类型错误
+pub(crate) fn merge(upstream: &mut Vec<Diagnostic>, extra: Vec<Diagnostic>) {
+    upstream.retain(|d| {
+        d.code != Some(NumberOrString::String(TYPE_ERROR.into()))
+            || !extra
+                .iter()
+                .filter(|e| e.severity == Some(DiagnosticSeverity::ERROR))
+                .map(covered_range)
+                .any(|range| range.start <= d.range.start && d.range.end <= range.end)
+    });
+    upstream.extend(extra);
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("{n} {word}")
+    } else {
+        format!("{n} {word}s")
+    }
+}
+
+/// 调用实参个数与函数声明的形参个数不一致
+fn arity(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Ident || !tokens.get(i + 1).is_some_and(|t| t.is(src, "(")) {
+            continue;
+        }
+        // 跳过声明本身和成员调用
+        let prev = i.checked_sub(1).map(|p| tokens[p].text(src));
+        if matches!(prev, Some("func" | ".")) {
+            continue;
+        }
+
+        let Some(func) = outline.resolve_at(tok.text(src), tok.start) else {
+            continue;
+        };
+        if outline.symbols[func].kind != SymbolKind::Function {
+            continue;
+        }
+        let Some(close) = outline::matching(src, tokens, i + 1) else {
+            continue;
+        };
+
+        let expected = outline.children(func).count();
+        let found = count_args(src, &tokens[i + 2..close]);
+        if expected == found {
+            continue;
+        }
+
+        let decl = &outline.symbols[func];
+        let mut diag = ctx.diagnostic(
+            (tok.start, tokens[close].end),
+            ARITY_MISMATCH,
+            format!("expected {}, found {}", plural(expected, "argument"), found),
+        );
+        diag.related_information = Some(vec![ctx.related(
+            decl.name_span,
+            format!("function `{}` declared here", decl.name),
+        )]);
+        out.push(diag);
+    }
+}
+
+/// 调用了不是函数的值，如 `x()` 而 `x: i64`。只报告类型确定不能调用的：
+/// 写出的基本类型或本文件的结构体，以及结构体名本身
+fn not_callable(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+    let plain = |ty: &str| {
+        is_numeric_type(ty)
+            || matches!(ty, "bool" | "str" | "String")
+            || outline
+                .symbols
+                .iter()
+                .any(|s| s.kind == SymbolKind::Struct && s.name == ty)
+    };
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Ident || !tokens.get(i + 1).is_some_and(|t| t.is(src, "(")) {
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| tokens[p].text(src));
+        if matches!(prev, Some("func" | ".")) {
+            continue;
+        }
+
+        let name = tok.text(src);
+        let Some(decl) = outline.resolve_at(name, tok.start) else {
+            continue;
+        };
+        let sym = &outline.symbols[decl];
+        let what = match (sym.kind, sym.ty.as_deref()) {
+            (SymbolKind::Struct, _) => format!("`{name}` is a struct, not a function"),
+            (SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Parameter, Some(ty))
+                if plain(ty) =>
+            {
+                format!("`{name}` is not callable; it has type `{ty}`")
+            }
+            _ => continue,
+        };
+
+        let mut diag = ctx.diagnostic((tok.start, tok.end), NOT_CALLABLE, what);
+        diag.related_information = Some(vec![
+            ctx.related(sym.name_span, format!("`{name}` declared here")),
+        ]);
+        if let Some(close) = outline::matching(src, tokens, i + 1) {
+            let call = (tok.start, tokens[close].end);
+            diag.data = Some(json!({ "covers": ctx.doc.lines.range(call) }));
+        }
+        out.push(diag);
+    }
+}
+
+/// 括号内顶层逗号分隔的实参个数，允许尾随逗号
+fn count_args(src: &str, inner: &[SyntaxToken]) -> usize {
+    if inner.is_empty() {
+        return 0;
+    }
+
+    let mut depth = 0i32;
+    let mut commas = 0;
+    for t in inner {
+        match t.text(src) {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            "," if depth == 0 => commas += 1,
+            _ => {}
+        }
+    }
+
+    let trailing = inner.last().is_some_and(|t| t.is(src, ","));
+    commas + 1 - usize::from(trailing)
+}
+
+/// 包含下标 `i` 的最内层 `{` 的下标
+fn enclosing_block(src: &str, tokens: &[SyntaxToken], i: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (k, t) in tokens[..i].iter().enumerate().rev() {
+        match t.text(src) {
+            "}" => depth += 1,
+            "{" if depth == 0 => return Some(k),
+            "{" => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 块中 `return` / `break` / `continue` 语句之后、块结束之前的语句
+fn unreachable(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+    // 已报告的范围内的跳转语句不再重复报告
+    let mut reported_until = 0;
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Keyword
+            || !matches!(tok.text(src), "return" | "break" | "continue")
+            || tok.start < reported_until
+        {
+            continue;
+        }
+        // 只看语句开头的跳转
+        let stmt_start = i
+            .checked_sub(1)
+            .is_none_or(|p| matches!(tokens[p].text(src), "{" | "}" | ";"));
+        if !stmt_start {
+            continue;
+        }
+
+        let Some(close) =
+            enclosing_block(src, tokens, i).and_then(|open| outline::matching(src, tokens, open))
+        else {
+            continue;
+        };
+        let Some(end) = outline::find_at_depth0(src, tokens, i, &[";", "}"]) else {
+            continue;
+        };
+
+        let first = end + 1;
+        if end >= close || first >= close {
+            continue;
+        }
+
+        let span = (tokens[first].start, tokens[close - 1].end);
+        let mut diag = ctx.diagnostic(span, UNREACHABLE_CODE, "unreachable code".into());
+        diag.severity = Some(DiagnosticSeverity::HINT);
+        diag.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+        out.push(diag);
+
+        reported_until = span.1;
+    }
+}
+
+/// 数字字面量的值，用于比较两个字面量
+fn number_value(text: &str) -> Option<f64> {
+    match integer_literal(text) {
+        Some(value) => value.map(|v| v as f64),
+        None => text.replace('_', "").parse().ok(),
+    }
+}
+
+/// 不依赖任何变量的条件的值：`true`、`false`、`!` 加常量，或两个字面量的比较
+fn constant_bool(src: &str, cond: &[SyntaxToken]) -> Option<bool> {
+    let texts: Vec<_> = cond.iter().map(|t| t.text(src)).collect();
+    match texts.as_slice() {
+        ["true"] => Some(true),
+        ["false"] => Some(false),
+        ["(", .., ")"] if outline::matching(src, cond, 0) == Some(cond.len() - 1) => {
+            constant_bool(src, &cond[1..cond.len() - 1])
+        }
+        ["!", ..] => constant_bool(src, &cond[1..]).map(|it| !it),
+        [_, op, _] => {
+            let (a, b) = (cond[0], cond[2]);
+            let ordering = match (a.kind, b.kind) {
+                (TokenKind::Number, TokenKind::Number) => {
+                    number_value(texts[0])?.partial_cmp(&number_value(texts[2])?)?
+                }
+                (TokenKind::Str, TokenKind::Str) if matches!(*op, "==" | "!=") => {
+                    texts[0].cmp(texts[2])
+                }
+                (TokenKind::Keyword, TokenKind::Keyword) if matches!(*op, "==" | "!=") => {
+                    let value = |t: &str| matches!(t, "true" | "false").then_some(t == "true");
+                    value(texts[0])?.cmp(&value(texts[2])?)
+                }
+                _ => return None,
+            };
+            match *op {
+                "==" => Some(ordering.is_eq()),
+                "!=" => Some(ordering.is_ne()),
+                "<" => Some(ordering.is_lt()),
+                ">" => Some(ordering.is_gt()),
+                "<=" => Some(ordering.is_le()),
+                ">=" => Some(ordering.is_ge()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `tokens[open]` 处的块后面 `else` 链最后一个 `}` 的下标
+fn else_chain_end(src: &str, tokens: &[SyntaxToken], open: usize) -> Option<usize> {
+    let mut close = outline::matching(src, tokens, open)?;
+    while tokens.get(close + 1).is_some_and(|t| t.is(src, "else")) {
+        let next = outline::find_at_depth0(src, tokens, close + 2, &["{"])?;
+        close = outline::matching(src, tokens, next)?;
+    }
+    Some(close)
+}
+
+/// `if`、`while` 的条件是常量，如遗留的调试代码 `if true`、`while false`；
+/// 永远不会执行的分支标为不必要。`while true` 是常见的无限循环写法，不报告
+fn constant_conditions(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        let keyword = tok.text(src);
+        if tok.kind != TokenKind::Keyword || !matches!(keyword, "if" | "while") {
+            continue;
+        }
+        let Some(open) = outline::find_at_depth0(src, tokens, i + 1, &["{"]) else {
+            continue;
+        };
+        let Some(close) = outline::matching(src, tokens, open) else {
+            continue;
+        };
+        if open == i + 1 {
+            continue;
+        }
+        let Some(value) = constant_bool(src, &tokens[i + 1..open]) else {
+            continue;
+        };
+
+        // 永远不会执行的分支：条件为假时是条件后的块，为真时是 `else` 及其后的整个链
+        let dead = match (keyword, value) {
+            ("while", true) => continue,
+            (_, false) => Some((open, close)),
+            (_, true) => tokens
+                .get(close + 1)
+                .filter(|t| t.is(src, "else"))
+                .and_then(|_| Some((close + 1, else_chain_end(src, tokens, open)?))),
+        };
+
+        let message = match (keyword, value) {
+            ("while", _) => "condition is always `false`; the loop body never runs".to_string(),
+            (_, true) if dead.is_some() => {
+                "condition is always `true`; the `else` branch is never taken".to_string()
+            }
+            _ => format!("condition is always `{value}`"),
+        };
+        let cond = (tokens[i + 1].start, tokens[open - 1].end);
+        let mut diag = ctx.diagnostic(cond, CONSTANT_CONDITION, message);
+        diag.severity = Some(DiagnosticSeverity::WARNING);
+        out.push(diag);
+
+        if let Some((from, to)) = dead {
+            let mut diag = ctx.diagnostic(
+                (tokens[from].start, tokens[to].end),
+                CONSTANT_CONDITION,
+                "this branch is never taken".into(),
+            );
+            diag.severity = Some(DiagnosticSeverity::HINT);
+            diag.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+            out.push(diag);
+        }
+    }
+}
+
+/// 块 `tokens[open..=close]` 末尾没有分号的表达式
+fn tail_expr(
+    src: &str,
+    tokens: &[SyntaxToken],
+    open: usize,
+    close: usize,
+) -> Option<(usize, usize)> {
+    let mut start = open + 1;
+    let mut k = start;
+    while k < close {
+        match outline::find_at_depth0(src, tokens, k, &[";"]) {
+            Some(semi) if semi < close => {
+                start = semi + 1;
+                k = semi + 1;
+            }
+            _ => break,
+        }
+    }
+    (start < close).then_some((start, close))
+}
+
+/// 从源码上能直接读出的表达式类型：带类型注解的变量，或声明了返回类型的函数调用
+pub(crate) fn written_type<'a>(ctx: &'a CheckContext, from: usize, to: usize) -> Option<&'a str> {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+    let name = ident(tokens, from)?;
+
+    let is_call = tokens.get(from + 1).is_some_and(|t| t.is(src, "("))
+        && outline::matching(src, tokens, from + 1) == Some(to - 1);
+    if to - from != 1 && !is_call {
+        return None;
+    }
+
+    let decl = &outline.symbols[outline.resolve_at(name.text(src), name.start)?];
+    match decl.kind {
+        SymbolKind::Function if is_call => decl.ty.as_deref(),
+        SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Parameter if !is_call => {
+            decl.ty.as_deref()
+        }
+        _ => None,
+    }
+}
+
+fn ident(tokens: &[SyntaxToken], i: usize) -> Option<SyntaxToken> {
+    tokens
+        .get(i)
+        .copied()
+        .filter(|t| t.kind == TokenKind::Ident)
+}
+
+/// 作为值使用的 `if .. else ..` 两个分支的类型不同
+fn branch_types(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Keyword || !tok.is(src, "if") {
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| tokens[p].text(src));
+        if !matches!(prev, Some("=" | "return")) {
+            continue;
+        }
+
+        let Some(then_open) = outline::find_at_depth0(src, tokens, i + 1, &["{"]) else {
+            continue;
+        };
+        let Some(then_close) = outline::matching(src, tokens, then_open) else {
+            continue;
+        };
+        let has_else = tokens
+            .get(then_close + 1)
+            .is_some_and(|t| t.is(src, "else"))
+            && tokens.get(then_close + 2).is_some_and(|t| t.is(src, "{"));
+        if !has_else {
+            continue;
+        }
+        let Some(else_close) = outline::matching(src, tokens, then_close + 2) else {
+            continue;
+        };
+
+        let (Some(then_tail), Some(else_tail)) = (
+            tail_expr(src, tokens, then_open, then_close),
+            tail_expr(src, tokens, then_close + 2, else_close),
+        ) else {
+            continue;
+        };
+        let (Some(then_ty), Some(else_ty)) = (
+            written_type(ctx, then_tail.0, then_tail.1),
+            written_type(ctx, else_tail.0, else_tail.1),
+        ) else {
+            continue;
+        };
+        if then_ty == else_ty {
+            continue;
+        }
+
+        let span_of = |(from, to): (usize, usize)| (tokens[from].start, tokens[to - 1].end);
+        let mut diag = ctx.diagnostic(
+            (tok.start, tokens[else_close].end),
+            BRANCH_TYPE_MISMATCH,
+            format!(
+                "`if` and `else` have incompatible types: \
+                 then branch is `{then_ty}`, else branch is `{else_ty}`"
+            ),
+        );
+        diag.related_information = Some(vec![
+            ctx.related(span_of(then_tail), format!("then branch is `{then_ty}`")),
+            ctx.related(span_of(else_tail), format!("else branch is `{else_ty}`")),
+        ]);
+        out.push(diag);
+    }
+}
+
+/// 在 `offset` 处还不可见、但在同一作用域或外层作用域中稍后声明的同名变量。
+/// 函数体内引用函数外稍后声明的变量不算，函数可能在声明之后才被调用
+fn later_declaration(outline: &Outline, name: &str, offset: usize) -> Option<usize> {
+    let crosses_function = |decl: usize| {
+        let at = outline.symbols[decl].name_span.0;
+        outline.symbols.iter().any(|f| {
+            f.kind == SymbolKind::Function
+                && f.span.0 <= offset
+                && offset < f.span.1
+                && !(f.span.0 <= at && at < f.span.1)
+        })
+    };
+
+    let mut scope = Some(outline.scope_at(offset));
+    while let Some(current) = scope {
+        let found = outline
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.scope == current && s.name == name)
+            .filter(|(_, s)| s.kind == SymbolKind::Variable && s.name_span.0 > offset)
+            .min_by_key(|(_, s)| s.name_span.0);
+
+        if let Some((idx, _)) = found {
+            return (!crosses_function(idx)).then_some(idx);
+        }
+        scope = outline.scopes[current].parent;
+    }
+
+    None
+}
+
+/// 变量在声明之前被使用
+fn use_before_declaration(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Ident || references::is_member(src, tokens, i) {
+            continue;
+        }
+        // 结构体定义和字面量中的字段名
+        let field = tokens.get(i + 1).is_some_and(|t| t.is(src, ":"))
+            && i.checked_sub(1)
+                .is_some_and(|p| matches!(tokens[p].text(src), "{" | ","));
+
+        let name = tok.text(src);
+        if field || outline.resolve_at(name, tok.start).is_some() {
+            continue;
+        }
+        let Some(decl) = later_declaration(outline, name, tok.start) else {
+            continue;
+        };
+
+        let mut diag = ctx.diagnostic(
+            (tok.start, tok.end),
+            USE_BEFORE_DECLARATION,
+            format!("`{name}` used before its declaration"),
+        );
+        diag.related_information = Some(vec![ctx.related(
+            outline.symbols[decl].name_span,
+            format!("`{name}` declared here"),
+        )]);
+        out.push(diag);
+    }
+}
+
+/// 执行到块末尾却没有返回值的位置（字节范围和说明）；块在所有路径上都返回时为 None。
+/// 块末尾没有分号的表达式视为块的值
+fn fall_through(
+    src: &str,
+    tokens: &[SyntaxToken],
+    open: usize,
+    close: usize,
+) -> Option<Vec<((usize, usize), &'static str)>> {
+    let block_end = (tokens[close].start, tokens[close].end);
+    // 最后一条语句是没有覆盖所有分支的 `if` 时，指出具体的分支
+    let mut last_if = None;
+    let mut k = open + 1;
+
+    while k < close {
+        let tok = tokens[k];
+        last_if = None;
+
+        match tok.text(src) {
+            "return" if tok.kind == TokenKind::Keyword => return None,
+            "{" => {
+                let end = outline::matching(src, tokens, k)?;
+                // 内层块总会返回时，之后的语句都不可达
+                fall_through(src, tokens, k, end)?;
+                k = end + 1;
+            }
+            "while" | "for" if tok.kind == TokenKind::Keyword => {
+                let body = outline::find_at_depth0(src, tokens, k + 1, &["{"])?;
+                let end = outline::matching(src, tokens, body)?;
+                // `while true` 没有跳出自身的 `break` 时不会结束；其他循环体可能一次也不执行
+                if tok.is(src, "while")
+                    && constant_bool(src, &tokens[k + 1..body]) == Some(true)
+                    && !breaks_out(src, tokens, body, end)
+                {
+                    return None;
+                }
+                k = end + 1;
+            }
+            "if" if tok.kind == TokenKind::Keyword => {
+                let mut holes = vec![];
+                let mut has_else = false;
+                let mut at = k;
+
+                loop {
+                    let body = outline::find_at_depth0(src, tokens, at + 1, &["{"])?;
+                    let end = outline::matching(src, tokens, body)?;
+                    holes.extend(fall_through(src, tokens, body, end).unwrap_or_default());
+                    k = end + 1;
+
+                    if !tokens.get(end + 1).is_some_and(|t| t.is(src, "else")) {
+                        break;
+                    }
+                    match tokens.get(end + 2) {
+                        Some(t) if t.is(src, "if") => at = end + 2,
+                        Some(t) if t.is(src, "{") => {
+                            let else_close = outline::matching(src, tokens, end + 2)?;
+                            holes.extend(
+                                fall_through(src, tokens, end + 2, else_close).unwrap_or_default(),
+                            );
+                            has_else = true;
+                            k = else_close + 1;
+                            break;
+                        }
+                        _ => break,
+                    }
+                }
+
+                if has_else && holes.is_empty() {
+                    return None;
+                }
+                if !has_else {
+                    holes.push(((tok.start, tok.end), "this `if` has no `else` branch"));
+                }
+                last_if = Some(holes);
+            }
+            _ => match outline::find_at_depth0(src, tokens, k, &[";"]) {
+                Some(semi) if semi < close => k = semi + 1,
+                _ => return None,
+            },
+        }
+
+        if tokens.get(k).is_some_and(|t| t.is(src, ";")) {
+            k += 1;
+        }
+    }
+
+    Some(last_if.unwrap_or_else(|| vec![(block_end, "this block ends without a value")]))
+}
+
+/// 循环体 `tokens[open..=close]` 中有跳出这个循环的 `break`（内层循环中的不算）
+fn breaks_out(src: &str, tokens: &[SyntaxToken], open: usize, close: usize) -> bool {
+    let mut k = open + 1;
+    while k < close {
+        let tok = tokens[k];
+        if tok.kind == TokenKind::Keyword && matches!(tok.text(src), "while" | "for") {
+            let inner = outline::find_at_depth0(src, tokens, k + 1, &["{"])
+                .and_then(|body| outline::matching(src, tokens, body));
+            match inner {
+                Some(end) if end < close => k = end + 1,
+                _ => return true,
+            }
+            continue;
+        }
+        if tok.kind == TokenKind::Keyword && tok.is(src, "break") {
+            return true;
+        }
+        k += 1;
+    }
+    false
+}
+
+/// 声明了返回类型的函数在某些路径上没有返回值，或者函数体为空
+fn missing_return(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for func in outline
+        .symbols
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Function)
+    {
+        if func.ty.as_deref().is_none_or(|ty| ty == "()") {
+            continue;
+        }
+
+        let name = tokens.partition_point(|t| t.start < func.name_span.0);
+        let Some(open) = outline::find_at_depth0(src, tokens, name + 1, &["{", ";"])
+            .filter(|i| tokens[*i].is(src, "{"))
+        else {
+            continue;
+        };
+        let Some(close) = outline::matching(src, tokens, open) else {
+            continue;
+        };
+        // 空函数体单独说明，比“不是所有路径都有返回值”更直接
+        if close == open + 1 {
+            let ty = func.ty.as_deref().unwrap_or_default();
+            out.push(ctx.diagnostic(
+                (tokens[open].start, tokens[close].end),
+                EMPTY_FUNCTION_BODY,
+                format!(
+                    "function `{}` is declared to return `{ty}` but its body is empty",
+                    func.name
+                ),
+            ));
+            continue;
+        }
+        let Some(holes) = fall_through(src, tokens, open, close) else {
+            continue;
+        };
+
+        let end = (tokens[close].start, tokens[close].end);
+        let mut diag = ctx.diagnostic(end, MISSING_RETURN, "not all paths return a value".into());
+        let related: Vec<_> = holes
+            .into_iter()
+            .filter(|(span, _)| *span != end)
+            .map(|(span, message)| ctx.related(span, message.into()))
+            .collect();
+        diag.related_information = (!related.is_empty()).then_some(related);
+        out.push(diag);
+    }
+}
+
+/// 函数声明中返回类型注解（`->` 之后）的字节范围
+fn return_type_span(ctx: &CheckContext, func: &outline::Symbol) -> Option<(usize, usize)> {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+    let name = tokens.partition_point(|t| t.start < func.name_span.0);
+    let arrow = outline::find_at_depth0(src, tokens, name + 1, &["->", "{", ";"])
+        .filter(|i| tokens[*i].is(src, "->"))?;
+    let end = outline::find_at_depth0(src, tokens, arrow + 1, &["{", ";"]).unwrap_or(tokens.len());
+    (end > arrow + 1).then(|| (tokens[arrow + 1].start, tokens[end - 1].end))
+}
+
+/// `return` 的值与所在函数声明的返回类型无关，如在返回 `i64` 的函数中 `return "x";`。
+/// 每个 `return` 各自报告；只看能直接读出类型的值，与比较运算的检查相同。
+/// 没有声明返回类型的函数中带值的 `return` 另外报告
+fn return_types(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Keyword || !tok.is(src, "return") {
+            continue;
+        }
+        let Some(func) = outline.enclosing_function(tok.start) else {
+            continue;
+        };
+        let func = &outline.symbols[func];
+        let Some(end) = outline::find_at_depth0(src, tokens, i + 1, &[";", "}"]) else {
+            continue;
+        };
+        let Some(declared) = func.ty.as_deref().filter(|ty| *ty != "()") else {
+            let value = (tokens[i + 1].start, tokens[end - 1].end);
+            if end > i + 1 && &src[value.0..value.1] != "()" {
+                out.push(unexpected_return_value(ctx, func, i + 1, end));
+            }
+            continue;
+        };
+
+        let (span, found) = if end == i + 1 {
+            ((tok.start, tok.end), "()".to_string())
+        } else {
+            let Some(found) = operand_type(ctx, i + 1, end) else {
+                continue;
+            };
+            if !unrelated(ctx, Operand::Written(declared), found) {
+                continue;
+            }
+            (
+                (tokens[i + 1].start, tokens[end - 1].end),
+                found.describe().to_string(),
+            )
+        };
+
+        let mut diag = ctx.diagnostic(
+            span,
+            RETURN_TYPE_MISMATCH,
+            format!(
+                "mismatched return type: function `{}` returns `{declared}`, found `{found}`",
+                func.name
+            ),
+        );
+        if let Some(annotation) = return_type_span(ctx, func) {
+            diag.related_information = Some(vec![ctx.related(
+                annotation,
+                format!("return type `{declared}` declared here"),
+            )]);
+        }
+        let stmt = (tok.start, tokens[end - 1].end);
+        diag.data = Some(json!({ "covers": ctx.doc.lines.range(stmt) }));
+        out.push(diag);
+    }
+}
+
+/// 没有声明返回类型（或返回 `()`）的函数中 `return` 带了值 `tokens[from..to]`
+fn unexpected_return_value(
+    ctx: &CheckContext,
+    func: &outline::Symbol,
+    from: usize,
+    to: usize,
+) -> Diagnostic {
+    let tokens = ctx.tokens;
+    let found = match operand_type(ctx, from, to) {
+        Some(found) => format!(" of type `{}`", found.describe()),
+        None => String::new(),
+    };
+
+    let mut diag = ctx.diagnostic(
+        (tokens[from].start, tokens[to - 1].end),
+        UNEXPECTED_RETURN_VALUE,
+        format!(
+            "function `{}` does not declare a return type, but this returns a value{found}",
+            func.name
+        ),
+    );
+    let note = format!("add `-> T` to `{}` if the value is intended", func.name);
+    diag.related_information = Some(vec![ctx.related(func.name_span, note)]);
+    let stmt = (tokens[from - 1].start, tokens[to - 1].end);
+    diag.data = Some(json!({ "covers": ctx.doc.lines.range(stmt) }));
+    diag
+}
+
+/// 数字字面量的值为 0，如 `0`、`0.0`、`0_000`、`0x0`
+fn is_zero_literal(text: &str) -> bool {
+    let digits = text.replace('_', "");
+    let lower = digits.to_ascii_lowercase();
+
+    let radix = ["0x", "0o", "0b"]
+        .iter()
+        .find_map(|p| lower.strip_prefix(p));
+    match radix {
+        Some(rest) => !rest.is_empty() && rest.chars().all(|c| c == '0'),
+        None => {
+            let mantissa = lower.split('e').next().unwrap_or_default();
+            mantissa.contains('0') && mantissa.chars().all(|c| c == '0' || c == '.')
+        }
+    }
+}
+
+/// 除数（或取模的模数）是字面量 0；只看字面量，不做常量折叠
+fn division_by_zero(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let Some(severity) = ctx.settings.division_by_zero.severity() else {
+        return;
+    };
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for (i, op) in tokens.iter().enumerate() {
+        if op.kind != TokenKind::Punct || !matches!(op.text(src), "/" | "%" | "/=" | "%=") {
+            continue;
+        }
+        let Some(zero) = tokens
+            .get(i + 1)
+            .filter(|t| t.kind == TokenKind::Number && is_zero_literal(t.text(src)))
+        else {
+            continue;
+        };
+
+        let what = if op.text(src).starts_with('/') {
+            "division"
+        } else {
+            "remainder"
+        };
+        let mut diag = ctx.diagnostic(
+            (op.start, zero.end),
+            DIVISION_BY_ZERO,
+            format!("{what} by zero"),
+        );
+        diag.severity = Some(severity);
+        out.push(diag);
+    }
+}
+
+/// `if`、`while` 的条件（去掉外层括号后）顶层是赋值 `x = y`，多半是想写 `==`。
+/// 建议放在 `data.suggestion` 中，快速修复把 `=` 换成 `==`
+fn assignment_in_condition(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let Some(severity) = ctx.settings.assignment_in_condition.severity() else {
+        return;
+    };
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Keyword || !matches!(tok.text(src), "if" | "while") {
+            continue;
+        }
+        let Some(open) = outline::find_at_depth0(src, tokens, i + 1, &["{"]) else {
+            continue;
+        };
+
+        let (mut from, mut to) = (i + 1, open);
+        while to > from + 1
+            && tokens[from].is(src, "(")
+            && outline::matching(src, tokens, from) == Some(to - 1)
+        {
+            from += 1;
+            to -= 1;
+        }
+        let Some(eq) = outline::find_at_depth0(src, &tokens[..to], from, &["="]) else {
+            continue;
+        };
+        if eq == from || ident(tokens, eq - 1).is_none() {
+            continue;
+        }
+
+        let mut diag = ctx.diagnostic(
+            (tokens[eq].start, tokens[eq].end),
+            ASSIGNMENT_IN_CONDITION,
+            "assignment used as a condition; did you mean `==`?".into(),
+        );
+        diag.severity = Some(severity);
+        diag.data = Some(json!({ "suggestion": "==" }));
+        out.push(diag);
+    }
+}
+
+/// 整数字面量的值，如 `255`、`1_000`、`0xff`；不是整数字面量时为 None，
+/// 超出 u128 时为 `Some(None)`
+fn integer_literal(text: &str) -> Option<Option<u128>> {
+    let digits = text.replace('_', "").to_ascii_lowercase();
+    let (radix, rest) = [("0x", 16), ("0o", 8), ("0b", 2)]
+        .iter()
+        .find_map(|(p, radix)| digits.strip_prefix(p).map(|rest| (*radix, rest)))
+        .unwrap_or((10, digits.as_str()));
+    if rest.is_empty() || !rest.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    Some(u128::from_str_radix(rest, radix).ok())
+}
+
+/// 整数类型的取值范围：`(最小值的绝对值, 最大值)`
+fn integer_bounds(ty: &str) -> Option<(u128, u128)> {
+    let signed = |bits: u32| (1u128 << (bits - 1), (1u128 << (bits - 1)) - 1);
+    let unsigned = |bits: u32| (0, u128::MAX >> (128 - bits));
+
+    Some(match ty {
+        "i8" => signed(8),
+        "i16" => signed(16),
+        "i32" => signed(32),
+        "i64" | "isize" => signed(64),
+        "i128" => signed(128),
+        "u8" => unsigned(8),
+        "u16" => unsigned(16),
+        "u32" => unsigned(32),
+        "u64" | "usize" => unsigned(64),
+        "u128" => unsigned(128),
+        _ => return None,
+    })
+}
+
+/// 从 `tokens[from]` 往前找同一层中未闭合的开括号，返回其下标和之间的顶层逗号数
+fn enclosing_open(src: &str, tokens: &[SyntaxToken], from: usize) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    let mut commas = 0;
+    for k in (0..=from).rev() {
+        match tokens[k].text(src) {
+            ")" | "]" | "}" => depth += 1,
+            "(" | "[" | "{" if depth == 0 => return Some((k, commas)),
+            "(" | "[" | "{" => depth -= 1,
+            "," if depth == 0 => commas += 1,
+            ";" if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 完整占据 `tokens[from..=to]` 的字面量所在位置期望的类型：变量的类型注解、赋值目标、
+/// 函数返回类型、实参对应的形参或结构体字段；没有注解的 `let` 取上游推断出的类型
+fn literal_target(ctx: &CheckContext, from: usize, to: usize) -> Option<String> {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+    let prev = from.checked_sub(1)?;
+    let next = tokens.get(to + 1).map(|t| t.text(src));
+
+    match tokens[prev].text(src) {
+        "=" if matches!(next, None | Some(";" | "}")) => {
+            let start = (0..prev)
+                .rev()
+                .find(|k| matches!(tokens[*k].text(src), ";" | "{" | "}"))
+                .map_or(0, |k| k + 1);
+            let name = match tokens[start].text(src) {
+                "let" | "const" => {
+                    let at = start + 1 + usize::from(tokens.get(start + 1)?.is(src, "mut"));
+                    ident(tokens, at)?
+                }
+                _ if prev == start + 1 => ident(tokens, start)?,
+                _ => return None,
+            };
+            let decl = outline.resolve_at(name.text(src), name.start)?;
+            let sym = &outline.symbols[decl];
+            match (&sym.ty, sym.kind) {
+                (Some(ty), SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Parameter) => {
+                    Some(ty.clone())
+                }
+                (None, SymbolKind::Variable | SymbolKind::Constant)
+                    if sym.name_span.0 == name.start =>
+                {
+                    inferred_type(ctx, decl)
+                }
+                _ => None,
+            }
+        }
+        "return" if matches!(next, None | Some(";" | "}")) => {
+            let func = outline.enclosing_function(tokens[from].start)?;
+            outline.symbols[func].ty.clone()
+        }
+        "(" | "," if matches!(next, Some(")" | ",")) => {
+            let (open, index) = enclosing_open(src, tokens, prev)?;
+            if !tokens[open].is(src, "(") {
+                return None;
+            }
+            let callee = ident(tokens, open.checked_sub(1)?)?;
+            let before = open.checked_sub(2).map(|k| tokens[k].text(src));
+            if matches!(before, Some("func" | ".")) {
+                return None;
+            }
+            let func = outline.resolve_at(callee.text(src), callee.start)?;
+            if outline.symbols[func].kind != SymbolKind::Function {
+                return None;
+            }
+            outline.children(func).nth(index)?.ty.clone()
+        }
+        ":" if matches!(next, Some("}" | ",")) => {
+            let field = ident(tokens, prev.checked_sub(1)?)?;
+            let (open, _) = enclosing_open(src, tokens, prev)?;
+            if !tokens[open].is(src, "{") {
+                return None;
+            }
+            let name = ident(tokens, open.checked_sub(1)?)?;
+            let item = outline.resolve_at(name.text(src), name.start)?;
+            if outline.symbols[item].kind != SymbolKind::Struct {
+                return None;
+            }
+            outline
+                .children(item)
+                .find(|s| s.name == field.text(src))?
+                .ty
+                .clone()
+        }
+        _ => None,
+    }
+}
+
+/// 上游为文件作用域中没有注解的绑定推断出的类型。类型表按名字记录，
+/// 同名的声明不止一个时分不清是哪一个，不给出
+fn inferred_type(ctx: &CheckContext, decl: usize) -> Option<String> {
+    let sym = &ctx.outline.symbols[decl];
+    let unique = ctx
+        .outline
+        .symbols
+        .iter()
+        .filter(|s| s.name == sym.name)
+        .count()
+        == 1;
+    if sym.scope != 0 || !unique {
+        return None;
+    }
+    ctx.table?
+        .var_map
+        .get(sym.name.as_str())
+        .map(|ty| ty.to_string())
+}
+
+/// 整数字面量超出它被赋给（或推断成）的整数类型的范围，如 `let x: u8 = 256;`
+fn integer_overflow(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Number {
+            continue;
+        }
+        let Some(value) = integer_literal(tok.text(src)) else {
+            continue;
+        };
+
+        // 前面的 `-` 是取负而不是减号时，算作字面量的一部分
+        let negative = i.checked_sub(1).is_some_and(|m| {
+            tokens[m].is(src, "-")
+                && m.checked_sub(1).is_none_or(|p| {
+                    let t = tokens[p];
+                    !matches!(
+                        t.kind,
+                        TokenKind::Ident | TokenKind::Number | TokenKind::Str
+                    ) && !matches!(t.text(src), ")" | "]" | "true" | "false")
+                })
+        });
+        let from = if negative { i - 1 } else { i };
+
+        let Some(ty) = literal_target(ctx, from, i) else {
+            continue;
+        };
+        let Some((min, max)) = integer_bounds(&ty) else {
+            continue;
+        };
+        let fits = value.is_some_and(|v| if negative { v <= min } else { v <= max });
+        if fits {
+            continue;
+        }
+
+        let span = (tokens[from].start, tok.end);
+        let min = if min == 0 {
+            "0".to_string()
+        } else {
+            format!("-{min}")
+        };
+        out.push(ctx.diagnostic(
+            span,
+            INTEGER_OVERFLOW,
+            format!(
+                "literal `{}` is out of range for `{ty}`, whose values are {min}..={max}",
+                &src[span.0..span.1]
+            ),
+        ));
+    }
+}
+
+/// 同一个函数中重名的参数，报告在后出现的参数上
+fn duplicate_params(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let outline = ctx.outline;
+
+    for (func, _) in outline
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.kind == SymbolKind::Function)
+    {
+        let params: Vec<_> = outline.children(func).collect();
+
+        for (k, param) in params.iter().enumerate() {
+            let Some(first) = params[..k].iter().find(|p| p.name == param.name) else {
+                continue;
+            };
+
+            let mut diag = ctx.diagnostic(
+                param.name_span,
+                DUPLICATE_PARAMETER,
+                format!("parameter `{}` is declared more than once", param.name),
+            );
+            diag.related_information = Some(vec![ctx.related(
+                first.name_span,
+                format!("`{}` first declared here", param.name),
+            )]);
+            out.push(diag);
+        }
+    }
+}
+
+/// 给没有 `mut` 的变量或常量赋值（包括 `+=` 等复合赋值）
+fn immutable_assignment(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        let Some(op) = tokens
+            .get(i + 1)
+            .filter(|t| matches!(t.text(src), "=" | "+=" | "-=" | "*=" | "/=" | "%="))
+        else {
+            continue;
+        };
+        // 只看语句开头的 `name = ..`，跳过声明和字段赋值
+        let stmt_start = i
+            .checked_sub(1)
+            .is_none_or(|p| matches!(tokens[p].text(src), "{" | "}" | ";"));
+        if tok.kind != TokenKind::Ident || !stmt_start {
+            continue;
+        }
+
+        let name = tok.text(src);
+        let Some(decl) = outline.resolve_at(name, tok.start) else {
+            continue;
+        };
+        let sym = &outline.symbols[decl];
+        let (message, note) = match sym.kind {
+            SymbolKind::Variable if !sym.mutable => (
+                format!(
+                    "cannot assign to immutable variable `{name}`; \
+                     declare it with `let mut {name}` to allow reassignment"
+                ),
+                format!("`{name}` is declared immutable here"),
+            ),
+            SymbolKind::Constant => (
+                format!("cannot assign to constant `{name}`; use a `let mut` variable instead"),
+                format!("`{name}` is declared as a constant here"),
+            ),
+            _ => continue,
+        };
+
+        let mut diag = ctx.diagnostic((tok.start, op.end), IMMUTABLE_ASSIGNMENT, message);
+        diag.related_information = Some(vec![ctx.related(sym.name_span, note)]);
+        out.push(diag);
+    }
+}
+
+/// 比较运算一侧的操作数能直接读出的类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand<'a> {
+    /// 写出的类型注解
+    Written(&'a str),
+    Number,
+    Str,
+    Bool,
+}
+
+impl Operand<'_> {
+    fn describe(&self) -> &str {
+        match self {
+            Operand::Written(ty) => ty,
+            Operand::Number => "{number}",
+            Operand::Str => "{string}",
+            Operand::Bool => "bool",
+        }
+    }
+}
+
+fn is_numeric_type(ty: &str) -> bool {
+    matches!(
+        ty,
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "f32"
+            | "f64"
+    )
+}
+
+/// 两个操作数的类型确定无关；不能确定（如注解是类型别名）时为 false
+fn unrelated(ctx: &CheckContext, a: Operand, b: Operand) -> bool {
+    let is_struct = |ty: &str| {
+        ctx.outline
+            .symbols
+            .iter()
+            .any(|s| s.kind == SymbolKind::Struct && s.name == ty)
+    };
+
+    match (a, b) {
+        (Operand::Written(a), Operand::Written(b)) => !a
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .eq(b.chars().filter(|c| !c.is_whitespace())),
+        (Operand::Written(ty), lit) | (lit, Operand::Written(ty)) => {
+            is_struct(ty)
+                || match lit {
+                    Operand::Number => ty == "bool",
+                    _ => is_numeric_type(ty),
+                }
+        }
+        (a, b) => a != b,
+    }
+}
+
+/// `tokens[from..to]` 是字面量、带注解的变量或声明了返回类型的函数调用时的类型
+fn operand_type<'a>(ctx: &'a CheckContext, from: usize, to: usize) -> Option<Operand<'a>> {
+    let tok = ctx.tokens[from];
+    if to - from == 1 {
+        match tok.kind {
+            TokenKind::Number => return Some(Operand::Number),
+            TokenKind::Str => return Some(Operand::Str),
+            TokenKind::Keyword if matches!(tok.text(ctx.src()), "true" | "false") => {
+                return Some(Operand::Bool);
+            }
+            _ => {}
+        }
+    }
+    written_type(ctx, from, to).map(Operand::Written)
+}
+
+/// 比较运算符左侧的操作数 `[from, op)`：单个 token，或 `f(..)` 形式的调用
+fn left_operand(src: &str, tokens: &[SyntaxToken], op: usize) -> Option<usize> {
+    let last = op.checked_sub(1)?;
+    if !tokens[last].is(src, ")") {
+        return Some(last);
+    }
+
+    let mut depth = 0usize;
+    for k in (0..=last).rev() {
+        match tokens[k].text(src) {
+            ")" | "]" | "}" => depth += 1,
+            "(" | "[" | "{" => {
+                depth -= 1;
+                if depth == 0 {
+                    return k
+                        .checked_sub(1)
+                        .filter(|f| tokens[*f].kind == TokenKind::Ident);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 比较运算符右侧的操作数 `(op, to)`
+fn right_operand(src: &str, tokens: &[SyntaxToken], op: usize) -> Option<usize> {
+    let first = tokens.get(op + 1)?;
+    if first.kind == TokenKind::Ident && tokens.get(op + 2).is_some_and(|t| t.is(src, "(")) {
+        return outline::matching(src, tokens, op + 2).map(|close| close + 1);
+    }
+    Some(op + 2)
+}
+
+/// 比较（`==`、`<` 等）两侧操作数的类型无关，如 `i64 == {string}`。
+/// 只检查两侧都是完整操作数的比较，`a + b == c` 这类需要推导的表达式不管
+fn incompatible_comparison(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+    let before = |k: Option<usize>| {
+        k.is_none_or(|k| {
+            matches!(
+                tokens[k].text(src),
+                "(" | "," | "=" | "{" | "}" | ";" | "&&" | "||" | "return" | "if" | "while"
+            )
+        })
+    };
+    let after = |k: usize| {
+        tokens
+            .get(k)
+            .is_none_or(|t| matches!(t.text(src), ")" | "," | ";" | "{" | "}" | "&&" | "||"))
+    };
+
+    for (i, op) in tokens.iter().enumerate() {
+        if op.kind != TokenKind::Punct
+            || !matches!(op.text(src), "==" | "!=" | "<" | ">" | "<=" | ">=")
+        {
+            continue;
+        }
+        let (Some(from), Some(to)) = (left_operand(src, tokens, i), right_operand(src, tokens, i))
+        else {
+            continue;
+        };
+        if !before(from.checked_sub(1)) || !after(to) {
+            continue;
+        }
+
+        let (Some(lhs), Some(rhs)) = (operand_type(ctx, from, i), operand_type(ctx, i + 1, to))
+        else {
+            continue;
+        };
+        if !unrelated(ctx, lhs, rhs) {
+            continue;
+        }
+
+        let (lhs, rhs) = (lhs.describe(), rhs.describe());
+        let span_of = |a: usize, b: usize| (tokens[a].start, tokens[b - 1].end);
+        let mut diag = ctx.diagnostic(
+            (op.start, op.end),
+            INCOMPATIBLE_COMPARISON,
+            format!(
+                "cannot compare `{lhs}` with `{rhs}` using `{}`: the types are unrelated",
+                op.text(src)
+            ),
+        );
+        diag.related_information = Some(vec![
+            ctx.related(span_of(from, i), format!("this is `{lhs}`")),
+            ctx.related(span_of(i + 1, to), format!("this is `{rhs}`")),
+        ]);
+        diag.data = Some(json!({ "covers": ctx.doc.lines.range(span_of(from, to)) }));
+        out.push(diag);
+    }
+}
+
+/// 结构体直接（不经过泛型、数组等间接类型）包含的结构体：`(字段, 字段类型对应的结构体)`
+fn direct_fields(outline: &Outline, item: usize) -> Vec<(usize, usize)> {
+    outline
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.parent == Some(item) && f.kind == SymbolKind::Field)
+        .filter_map(|(field, f)| {
+            let ty = f.ty.as_deref()?.trim();
+            let target = outline.resolve(ty, f.name_span.0)?;
+            (outline.symbols[target].kind == SymbolKind::Struct).then_some((field, target))
+        })
+        .collect()
+}
+
+/// 从 `from` 出发沿直接包含的字段回到 `start` 的路径（经过的字段）
+fn cycle_path(
+    outline: &Outline,
+    start: usize,
+    from: usize,
+    visited: &mut Vec<usize>,
+    path: &mut Vec<usize>,
+) -> bool {
+    for (field, target) in direct_fields(outline, from) {
+        path.push(field);
+        if target == start {
+            return true;
+        }
+        if !visited.contains(&target) {
+            visited.push(target);
+            if cycle_path(outline, start, target, visited, path) {
+                return true;
+            }
+        }
+        path.pop();
+    }
+    false
+}
+
+/// 直接或经过其他结构体包含自身的结构体，大小无限。
+/// 每个环只报告一次，报告在环中最先声明的结构体上，环经过的字段放在相关信息中
+fn recursive_types(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let outline = ctx.outline;
+
+    for (item, sym) in outline
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.kind == SymbolKind::Struct)
+    {
+        let mut path = vec![];
+        if !cycle_path(outline, item, item, &mut vec![item], &mut path) {
+            continue;
+        }
+        // 环中的结构体都会找到同一个环，只在最先声明的上报告
+        let members: Vec<usize> = path
+            .iter()
+            .filter_map(|field| outline.symbols[*field].parent)
+            .collect();
+        if members.iter().any(|it| *it < item) {
+            continue;
+        }
+
+        let mut diag = ctx.diagnostic(
+            sym.name_span,
+            RECURSIVE_TYPE,
+            format!(
+                "recursive type `{}` has infinite size; \
+                 the cycle must go through a type that stores its elements indirectly",
+                sym.name
+            ),
+        );
+        diag.related_information = Some(
+            path.iter()
+                .map(|field| {
+                    let f = &outline.symbols[*field];
+                    let owner = f.parent.map_or("", |it| outline.symbols[it].name.as_str());
+                    let ty = f.ty.as_deref().unwrap_or_default().trim();
+                    ctx.related(f.name_span, format!("`{owner}` contains `{ty}` here"))
+                })
+                .collect(),
+        );
+        out.push(diag);
+    }
+}
+
+/// 注释中作为单词出现的标记（`TODO`、`FIXME` 等），诊断消息是标记所在行的其余内容
+fn todo_comments(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let src = ctx.src();
+    let keywords = &ctx.settings.todo.keywords;
+    let comments = ctx
+        .doc
+        .tokens
+        .iter()
+        .filter(|t| matches!(t.kind, TokenKind::Comment | TokenKind::DocComment));
+
+    for tok in comments {
+        let text = tok.text(src);
+
+        for (at, _) in text.char_indices() {
+            let Some(keyword) = keywords
+                .iter()
+                .find(|k| !k.is_empty() && text[at..].starts_with(k.as_str()))
+            else {
+                continue;
+            };
+            let end = at + keyword.len();
+            let bounded =
+                !text[..at].ends_with(is_ident_char) && !text[end..].starts_with(is_ident_char);
+            if !bounded {
+                continue;
+            }
+
+            let line = text[at..].lines().next().unwrap_or_default();
+            let message = line.trim_end().trim_end_matches("*/").trim_end();
+            let span = (tok.start + at, tok.start + end);
+            let mut diag = ctx.diagnostic(span, TODO_COMMENT, message.into());
+            diag.severity = Some(DiagnosticSeverity::INFORMATION);
+            out.push(diag);
+        }
+    }
+}
+
+/// 补全缺少的字段时填入的值：基本类型的零值，其他类型留一个注释占位
+fn placeholder(ty: Option<&str>) -> String {
+    match ty {
+        Some("f32" | "f64") => "0.0".into(),
+        Some(ty) if is_numeric_type(ty) => "0".into(),
+        Some("bool") => "false".into(),
+        Some("str" | "String") => "\"\"".into(),
+        Some(ty) => format!("/* {ty} */"),
+        None => "/* value */".into(),
+    }
+}
+
+/// 行首的空白
+fn line_indent(src: &str, offset: usize) -> &str {
+    let start = src[..offset].rfind('\n').map_or(0, |it| it + 1);
+    let line = &src[start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// 在结构体字面量 `tokens[open..=close]` 末尾加上 `missing` 字段的编辑，
+/// 单行的字面量加在同一行，多行的每个字段一行
+fn fill_fields(
+    ctx: &CheckContext,
+    open: usize,
+    close: usize,
+    entries: &[(usize, usize)],
+    missing: &[&outline::Symbol],
+) -> TextEdit {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+    let fields: Vec<_> = missing
+        .iter()
+        .map(|f| format!("{}: {}", f.name, placeholder(f.ty.as_deref())))
+        .collect();
+    let single_line = !src[tokens[open].end..tokens[close].start].contains('\n');
+    let trailing_comma = tokens[close - 1].is(src, ",");
+
+    let (span, text) = match entries.last() {
+        None if single_line => (
+            (tokens[open].end, tokens[close].start),
+            format!(" {} ", fields.join(", ")),
+        ),
+        None => {
+            let outer = line_indent(src, tokens[close].start);
+            let lines: String = fields
+                .iter()
+                .map(|f| format!("\n{outer}    {f},"))
+                .collect();
+            (
+                (tokens[open].end, tokens[close].start),
+                format!("{lines}\n{outer}"),
+            )
+        }
+        Some(&(first, last)) => {
+            let at = if trailing_comma {
+                tokens[close - 1].end
+            } else {
+                tokens[last].end
+            };
+            let comma = if trailing_comma { "" } else { "," };
+            let text = if single_line {
+                format!("{comma} {}", fields.join(", "))
+            } else {
+                let indent = line_indent(src, tokens[first].start);
+                let lines: String = fields.iter().map(|f| format!("\n{indent}{f},")).collect();
+                format!("{comma}{lines}")
+            };
+            ((at, at), text)
+        }
+    };
+
+    TextEdit {
+        range: ctx.doc.lines.range(span),
+        new_text: text,
+    }
+}
+
+/// 结构体字面量 `S { .. }` 中结构体没有的字段（给出最接近的字段名作为建议），
+/// 以及没有写出的字段。快速修复删掉未知字段或补上缺少的字段，编辑放在 `data` 中
+fn struct_literals(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Ident || !tokens.get(i + 1).is_some_and(|t| t.is(src, "{")) {
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| tokens[p]);
+        if prev.is_some_and(|p| {
+            (p.kind == TokenKind::Keyword && !p.is(src, "return")) || p.is(src, ".")
+        }) {
+            continue;
+        }
+        let Some(item) = outline.resolve_at(tok.text(src), tok.start) else {
+            continue;
+        };
+        let decl = &outline.symbols[item];
+        if decl.kind != SymbolKind::Struct || decl.name_span.0 == tok.start {
+            continue;
+        }
+        let Some(close) = outline::matching(src, tokens, i + 1) else {
+            continue;
+        };
+
+        // 顶层逗号分隔的各项 `[first, last]`
+        let mut entries = vec![];
+        let mut depth = 0usize;
+        let mut first = i + 2;
+        for k in i + 2..=close {
+            match tokens[k].text(src) {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" if depth > 0 => depth -= 1,
+                "}" if depth > 0 => depth -= 1,
+                "," | "}" if depth == 0 => {
+                    if first < k {
+                        entries.push((first, k - 1));
+                    }
+                    first = k + 1;
+                }
+                _ => {}
+            }
+        }
+        // `..base` 展开时其余字段来自 base
+        let spread = entries
+            .iter()
+            .any(|(first, _)| tokens[*first].is(src, ".."));
+
+        let fields: Vec<_> = outline
+            .children(item)
+            .filter(|f| f.kind == SymbolKind::Field)
+            .collect();
+        // 各项写出的字段名，不是 `name: value` 或 `name` 形式的项为 None
+        let names: Vec<_> = entries
+            .iter()
+            .map(|&(first, last)| {
+                ident(tokens, first).filter(|_| first == last || tokens[first + 1].is(src, ":"))
+            })
+            .collect();
+        let present: Vec<_> = names.iter().flatten().map(|name| name.text(src)).collect();
+
+        for (n, (&(_, last), name)) in entries.iter().zip(&names).enumerate() {
+            let Some(name) = name else {
+                continue;
+            };
+            let field = name.text(src);
+            if fields.iter().any(|f| f.name == field) {
+                continue;
+            }
+
+            // 删除这一项和它后面的逗号；最后一项删除前面的逗号
+            let remove = match entries.get(n + 1) {
+                Some(&(next, _)) => (name.start, tokens[next].start),
+                None if n > 0 => (tokens[entries[n - 1].1 + 1].start, tokens[last].end),
+                None => (name.start, tokens[close].start),
+            };
+            let unused = fields
+                .iter()
+                .map(|f| f.name.as_str())
+                .filter(|it| !present.contains(it));
+            let suggestion = closest(field, unused);
+
+            let mut message = format!("struct `{}` has no field named `{field}`", decl.name);
+            if let Some(suggestion) = suggestion {
+                message = format!("{message}; did you mean `{suggestion}`?");
+            }
+            let mut diag = ctx.diagnostic((name.start, name.end), UNKNOWN_FIELD, message);
+            diag.related_information = Some(vec![ctx.related(
+                decl.name_span,
+                format!("struct `{}` declared here", decl.name),
+            )]);
+            let mut data = json!({ "remove": ctx.doc.lines.range(remove) });
+            if let Some(suggestion) = suggestion {
+                data["suggestion"] = json!(suggestion);
+            }
+            diag.data = Some(data);
+            out.push(diag);
+        }
+
+        let missing: Vec<_> = fields
+            .iter()
+            .filter(|f| !present.contains(&f.name.as_str()))
+            .copied()
+            .collect();
+        if spread || missing.is_empty() {
+            continue;
+        }
+        let listed: Vec<_> = missing.iter().map(|f| format!("`{}`", f.name)).collect();
+        let what = if missing.len() == 1 {
+            "field"
+        } else {
+            "fields"
+        };
+        let mut diag = ctx.diagnostic(
+            (tok.start, tok.end),
+            MISSING_FIELDS,
+            format!(
+                "missing {what} {} in `{}` literal",
+                listed.join(", "),
+                decl.name
+            ),
+        );
+        diag.data = Some(json!({ "fill": fill_fields(ctx, i + 1, close, &entries, &missing) }));
+        out.push(diag);
+    }
+}
+
+/// 本文件中名为 `ty` 的结构体
+fn local_struct(outline: &Outline, ty: &str) -> Option<usize> {
+    outline
+        .symbols
+        .iter()
+        .position(|s| s.kind == SymbolKind::Struct && s.name == ty)
+}
+
+/// `tokens[dot]` 处 `.` 左边的值能直接读出的类型：带注解的变量、声明了返回类型的调用，
+/// 或类型已知的值的字段（`a.b.c` 中的 `a.b`）
+fn receiver_type<'a>(ctx: &'a CheckContext, dot: usize) -> Option<&'a str> {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+    let last = dot.checked_sub(1)?;
+
+    let member = |k: usize| k.checked_sub(1).is_some_and(|p| tokens[p].is(src, "."));
+
+    if tokens[last].kind == TokenKind::Ident && member(last) {
+        let owner = local_struct(outline, receiver_type(ctx, last - 1)?)?;
+        return outline
+            .children(owner)
+            .find(|f| f.name == tokens[last].text(src))?
+            .ty
+            .as_deref();
+    }
+    // 成员调用 `a.f()` 的返回类型不知道
+    let from = left_operand(src, tokens, dot).filter(|from| !member(*from))?;
+    written_type(ctx, from, dot)
+}
+
+/// 访问类型上不存在的字段 `value.field`，建议最接近的字段名（见 `suggestion_actions`）。
+/// 只检查类型是本文件结构体的值；后面跟着 `(` 的调用不管
+fn field_access(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if !tok.is(src, ".") {
+            continue;
+        }
+        let Some(name) = ident(tokens, i + 1) else {
+            continue;
+        };
+        if tokens.get(i + 2).is_some_and(|t| t.is(src, "(")) {
+            continue;
+        }
+        let Some(ty) = receiver_type(ctx, i) else {
+            continue;
+        };
+        let Some(owner) = local_struct(outline, ty) else {
+            continue;
+        };
+
+        let field = name.text(src);
+        let fields: Vec<_> = outline
+            .children(owner)
+            .filter(|f| f.kind == SymbolKind::Field)
+            .collect();
+        if fields.iter().any(|f| f.name == field) {
+            continue;
+        }
+
+        let suggestion = closest(field, fields.iter().map(|f| f.name.as_str()));
+
+        let mut message = format!("no field `{field}` on type `{ty}`");
+        if let Some(suggestion) = suggestion {
+            message = format!("{message}; did you mean `{suggestion}`?");
+        }
+        let mut diag = ctx.diagnostic((name.start, name.end), NO_SUCH_FIELD, message);
+        let decl = &outline.symbols[owner];
+        diag.related_information =
+            Some(vec![ctx.related(
+                decl.name_span,
+                format!("struct `{ty}` declared here"),
+            )]);
+        if let Some(suggestion) = suggestion {
+            diag.data = Some(json!({ "suggestion": suggestion }));
+        }
+        out.push(diag);
+    }
+}
+
+/// 两个名字之间的编辑距离（按字符）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diag
+            } else {
+                1 + diag.min(above).min(row[j])
+            };
+            diag = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// `candidates` 中与 `name` 最接近的；距离超过名字长度的三分之一（至少 1）时不给建议，
+/// 距离相同时取先出现的
+fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+
+    candidates
+        .map(|it| (edit_distance(name, it), it))
+        .filter(|(distance, _)| (1..=limit).contains(distance))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, it)| it)
+}
+
+/// 在 `offset` 处可见的名字中与 `name` 最接近的，内层作用域的名字优先
+fn closest_name(
+    ctx: &CheckContext,
+    builtins: &TypeTable,
+    name: &str,
+    offset: usize,
+) -> Option<String> {
+    let visible = symbols::visible_symbols(ctx.doc, offset, builtins);
+    closest(name, visible.iter().map(|it| it.name.as_str())).map(str::to_string)
+}
+
+/// 上游报告在未声明的标识符上的类型错误，在消息后加上拼写建议，
+/// 建议的名字放在 `data.suggestion` 中供快速修复使用
+pub(crate) fn suggest_names(
+    ctx: &CheckContext,
+    builtins: &TypeTable,
+    diagnostics: &mut [Diagnostic],
+) {
+    let (src, tokens, outline) = (ctx.src(), ctx.tokens, ctx.outline);
+    let code = NumberOrString::String(TYPE_ERROR.into());
+
+    for diag in diagnostics
+        .iter_mut()
+        .filter(|it| it.code.as_ref() == Some(&code))
+    {
+        let offset = ctx.doc.lines.offset(diag.range.start);
+        let Some(idx) = tokens.iter().position(|t| t.start == offset) else {
+            continue;
+        };
+        let tok = tokens[idx];
+        // 快速修复直接替换诊断范围，范围必须正好是这个标识符
+        let exact = ctx.doc.lines.offset(diag.range.end) == tok.end;
+        if !exact || tok.kind != TokenKind::Ident || references::is_member(src, tokens, idx) {
+            continue;
+        }
+
+        let name = tok.text(src);
+        if outline.resolve_at(name, tok.start).is_some() || builtins.var_map.contains_key(name) {
+            continue;
+        }
+        let Some(suggestion) = closest_name(ctx, builtins, name, tok.start) else {
+            continue;
+        };
+
+        diag.message = format!("{}; did you mean `{suggestion}`?", diag.message);
+        diag.data = Some(json!({ "suggestion": suggestion }));
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+use tower_lsp::jsonrpc::{Error, Result};
+use tower_lsp::lsp_types::*;
+
+use crate::checks::{MISSING_FIELDS, UNKNOWN_FIELD};
+use crate::document::Document;
+use crate::references;
+use crate::style::REDUNDANT_ANNOTATION;
+use crate::syntax::{self, TokenKind};
+use crate::workspace::relative_import_path;
+use crate::{Backend, TYPE_ERROR};
+
+impl Backend {
+    /// 针对 `diagnostics` 的所有快速修复
+    pub(crate) async fn quick_fixes(
+        &self,
+        uri: &Url,
+        doc: &Document,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<CodeActionOrCommand> {
+        let mut actions = self.import_actions(uri, doc, diagnostics).await;
+        actions.extend(annotation_actions(uri, diagnostics));
+        actions.extend(suggestion_actions(uri, diagnostics));
+        actions.extend(field_actions(uri, diagnostics));
+        actions
+    }
+
+    /// `typedAnt.fixAll`：参数为 `TextDocumentIdentifier`。应用文档中所有确定的修复
+    /// （`isPreferred` 的快速修复；有多个候选的 import 不算），与已选修复重叠的跳过。
+    /// 返回 `{ applied, skipped }`
+    pub(crate) async fn fix_all(
+        &self,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<Option<serde_json::Value>> {
+        let params: TextDocumentIdentifier = arguments
+            .into_iter()
+            .next()
+            .and_then(|it| serde_json::from_value(it).ok())
+            .ok_or_else(|| Error::invalid_params("expected TextDocumentIdentifier"))?;
+        let uri = params.uri;
+
+        let diagnostics = match self.diagnostics.read().await.get(&uri) {
+            Some(it) => self.settings.read().await.diagnostics.filter(it),
+            None => vec![],
+        };
+
+        let mut edits: Vec<TextEdit> = {
+            let docs = self.documents.read().await;
+            let Some(doc) = docs.get(&uri) else {
+                return Ok(None);
+            };
+
+            self.quick_fixes(&uri, doc, &diagnostics)
+                .await
+                .into_iter()
+                .filter_map(|it| match it {
+                    CodeActionOrCommand::CodeAction(action) => Some(action),
+                    CodeActionOrCommand::Command(_) => None,
+                })
+                .filter(|action| action.is_preferred == Some(true))
+                .filter_map(|action| action.edit?.changes?.remove(&uri))
+                .flatten()
+                .collect()
+        };
+        edits.sort_by_key(|it| (it.range.start, it.range.end));
+
+        let total = edits.len();
+        let mut merged: Vec<TextEdit> = vec![];
+        for edit in edits {
+            let overlaps = merged
+                .last()
+                .is_some_and(|last| last.range.end > edit.range.start);
+            if !overlaps {
+                merged.push(edit);
+            }
+        }
+        let skipped = total - merged.len();
+
+        let mut applied = merged.len();
+        if applied > 0 {
+            let changes = HashMap::from([(uri.clone(), merged)]);
+            let response = self.client.apply_edit(WorkspaceEdit::new(changes)).await;
+            if !response.is_ok_and(|it| it.applied) {
+                applied = 0;
+            }
+        }
+
+        let message = match skipped {
+            0 => format!("applied {applied} fixes"),
+            _ => format!("applied {applied} fixes, skipped {skipped} conflicting"),
+        };
+        self.client.show_message(MessageType::INFO, message).await;
+
+        Ok(Some(json!({ "applied": applied, "skipped": skipped })))
+    }
+
+    /// 对上游报告的未解析标识符（不是字段或成员），为每个导出了同名符号的工作区文件
+    /// 提供一个 import 修复
+    pub(crate) async fn import_actions(
+        &self,
+        uri: &Url,
+        doc: &Document,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<CodeActionOrCommand> {
+        let Ok(current_path) = uri.to_file_path() else {
+            return vec![];
+        };
+
+        let text = doc.text.as_str();
+        let outline = &doc.outline;
+        let tokens = syntax::code_tokens(&doc.tokens);
+        let index = self.workspace.read().await;
+
+        let insert_at = import_position(doc);
+        let code = NumberOrString::String(TYPE_ERROR.into());
+
+        let mut actions = vec![];
+
+        for diag in diagnostics
+            .iter()
+            .filter(|it| it.code.as_ref() == Some(&code))
+        {
+            let start = doc.lines.offset(diag.range.start);
+            let end = doc.lines.offset(diag.range.end);
+            let Some(idx) = tokens.iter().position(|t| t.start == start) else {
+                continue;
+            };
+            let tok = tokens[idx];
+            if tok.end != end
+                || tok.kind != TokenKind::Ident
+                || references::is_member(text, &tokens, idx)
+            {
+                continue;
+            }
+
+            let name = tok.text(text);
+            if outline.resolve_at(name, start).is_some() {
+                continue;
+            }
+
+            for (target, _) in index.find(uri, name) {
+                if target == uri {
+                    continue;
+                }
+                let Ok(target_path) = target.to_file_path() else {
+                    continue;
+                };
+
+                let path = relative_import_path(&current_path, &target_path);
+                if outline.imports.iter().any(|it| it.path == path) {
+                    continue;
+                }
+
+                let edit = TextEdit {
+                    range: Range::new(insert_at, insert_at),
+                    new_text: format!("import \"{path}\";\n"),
+                };
+
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Import `{name}` from \"{path}\""),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diag.clone()]),
+                    edit: Some(WorkspaceEdit::new(HashMap::from([(
+                        uri.clone(),
+                        vec![edit],
+                    )]))),
+                    ..Default::default()
+                }));
+            }
+        }
+
+        actions
+    }
+}
+
+/// 新的 import 放在已有 import 之后，没有的话放在文件开头
+pub(crate) fn import_position(doc: &Document) -> Position {
+    doc.outline
+        .imports
+        .iter()
+        .map(|it| doc.lines.position(it.stmt_span.1))
+        .max()
+        .map_or(Position::new(0, 0), |pos| Position::new(pos.line + 1, 0))
+}
+
+/// 删除多余的类型注解：诊断范围就是要删除的 `: T`
+pub(crate) fn annotation_actions(
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    let code = NumberOrString::String(REDUNDANT_ANNOTATION.into());
+
+    diagnostics
+        .iter()
+        .filter(|diag| diag.code.as_ref() == Some(&code))
+        .map(|diag| {
+            let edit = TextEdit {
+                range: diag.range,
+                new_text: String::new(),
+            };
+
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: "Remove redundant type annotation".into(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diag.clone()]),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    uri.clone(),
+                    vec![edit],
+                )]))),
+                is_preferred: Some(true),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// 把拼错的名字替换成诊断中建议的名字（见 `checks::suggest_names`）
+pub(crate) fn suggestion_actions(
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    diagnostics
+        .iter()
+        .filter_map(|diag| {
+            let suggestion = diag.data.as_ref()?.get("suggestion")?.as_str()?;
+            let edit = TextEdit {
+                range: diag.range,
+                new_text: suggestion.to_string(),
+            };
+
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Change to `{suggestion}`"),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diag.clone()]),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    uri.clone(),
+                    vec![edit],
+                )]))),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+/// 删除结构体字面量中的未知字段，或补上缺少的字段（编辑见 `checks::struct_literals`）。
+/// 有拼写建议时优先改名，删除不作为首选
+pub(crate) fn field_actions(uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    let unknown = NumberOrString::String(UNKNOWN_FIELD.into());
+    let missing = NumberOrString::String(MISSING_FIELDS.into());
+
+    diagnostics
+        .iter()
+        .filter_map(|diag| {
+            let data = diag.data.as_ref()?;
+            let (title, edit, preferred) = if diag.code.as_ref() == Some(&unknown) {
+                let range: Range = serde_json::from_value(data.get("remove")?.clone()).ok()?;
+                let edit = TextEdit {
+                    range,
+                    new_text: String::new(),
+                };
+                (
+                    "Remove unknown field",
+                    edit,
+                    data.get("suggestion").is_none(),
+                )
+            } else if diag.code.as_ref() == Some(&missing) {
+                let edit: TextEdit = serde_json::from_value(data.get("fill")?.clone()).ok()?;
+                ("Add missing fields", edit, false)
+            } else {
+                return None;
+            };
+
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: title.into(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diag.clone()]),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    uri.clone(),
+                    vec![edit],
+                )]))),
+                is_preferred: preferred.then_some(true),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
@@ -0,0 +1,903 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ant_type_checker::table::TypeTable;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tower_lsp::lsp_types::*;
+
+use crate::cache::content_hash;
+use crate::code_action::import_position;
+use crate::config::CallParens;
+use crate::document::Document;
+use crate::outline::{self, Outline, SymbolKind};
+use crate::syntax::{self, SyntaxToken, TokenKind, is_ident_char};
+use crate::types::BUILTIN_TYPES;
+use crate::workspace::{IndexedSymbol, SOURCE_EXT, relative_import_path, resolve_import};
+use crate::{Backend, analyze};
+
+/// 其他文件中符号的补全项放在 `CompletionItem::data` 中的信息，resolve 时据此生成 import
+#[derive(Debug, Serialize, Deserialize)]
+struct ImportData {
+    /// 正在补全的文件
+    uri: Url,
+    /// 定义符号的文件
+    target: Url,
+}
+
+/// 获取光标前的标识符（UTF-8 / UTF-16 安全）
+///
+/// `position.character` 按协商出的位置编码计数（默认 UTF-16 码元），制表符只算一个单位，
+/// 因此无论缩进用制表符还是空格，换算出的字节偏移都落在光标处而不是缩进中间。
+pub(crate) fn current_ident(doc: &Document, position: Position) -> String {
+    let offset = doc.lines.offset(position);
+    let before = &doc.text[..offset];
+
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_ident_char(*c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+
+    before[start..].to_string()
+}
+
+/// 光标前的限定名 `a::b::c` 或 `a.b.c`，拆成限定部分（`[a, b]`）和正在输入的最后一段（`c`）
+pub(crate) fn qualified_ident(doc: &Document, position: Position) -> (Vec<String>, String) {
+    let partial = current_ident(doc, position);
+    let mut before = &doc.text[..doc.lines.offset(position) - partial.len()];
+    let mut qualifier = vec![];
+
+    while let Some(rest) = before
+        .strip_suffix("::")
+        .or_else(|| before.strip_suffix('.'))
+    {
+        let start = rest
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_ident_char(*c))
+            .last()
+            .map_or(rest.len(), |(i, _)| i);
+
+        let segment = &rest[start..];
+        if !segment.starts_with(syntax::is_ident_start) {
+            break;
+        }
+        qualifier.push(segment.to_string());
+        before = &rest[..start];
+    }
+
+    qualifier.reverse();
+    (qualifier, partial)
+}
+
+/// 光标在字符串或注释内部（不在变量补全的上下文中）
+pub(crate) fn in_string_or_comment(doc: &Document, offset: usize) -> bool {
+    let text = doc.text.as_str();
+    let idx = doc.tokens.partition_point(|t| t.end < offset);
+
+    doc.tokens[idx..].iter().take(2).any(|t| {
+        let body = t.text(text);
+        // 紧贴在结束符之后已经在外面了，行注释一直延续到行尾
+        let closed = match t.kind {
+            TokenKind::Str => body.len() > 1 && body.ends_with('"'),
+            TokenKind::Comment if body.starts_with("/*") => body.len() > 3 && body.ends_with("*/"),
+            TokenKind::Comment | TokenKind::DocComment => false,
+            _ => return false,
+        };
+
+        t.start < offset && (offset < t.end || (offset == t.end && !closed))
+    })
+}
+
+/// 光标前紧挨着的 token 的种类（光标在 token 末尾或中间）
+fn kind_before(doc: &Document, offset: usize) -> Option<TokenKind> {
+    let idx = doc.tokens.partition_point(|t| t.end < offset);
+    doc.tokens
+        .get(idx)
+        .filter(|t| t.start < offset)
+        .map(|t| t.kind)
+}
+
+/// 需要弹出补全的位置：不在数字字面量（`1_000`）中；
+/// 由 `_` 触发时 `_` 必须是标识符的一部分，由 `.` 触发时 `.` 前面要能给出后缀补全
+pub(crate) fn wants_completion(doc: &Document, offset: usize, trigger: Option<&str>) -> bool {
+    let kind = kind_before(doc, offset);
+    match trigger {
+        Some("_") => kind == Some(TokenKind::Ident),
+        Some(".") => {
+            let tokens = syntax::code_tokens(&doc.tokens);
+            postfix_receiver(&doc.text, &tokens, &doc.outline, offset, "").is_some()
+        }
+        _ => kind != Some(TokenKind::Number),
+    }
+}
+
+fn snippet_support(caps: &ClientCapabilities) -> bool {
+    caps.text_document
+        .as_ref()
+        .and_then(|it| it.completion.as_ref())
+        .and_then(|it| it.completion_item.as_ref())
+        .and_then(|it| it.snippet_support)
+        .unwrap_or(false)
+}
+
+/// 函数补全：`call` 为真时插入带参数占位符的调用，否则只插入名字
+fn function_item(outline: &Outline, func: usize, call: bool) -> CompletionItem {
+    let name = &outline.symbols[func].name;
+    let params: Vec<&str> = outline.children(func).map(|p| p.name.as_str()).collect();
+
+    let mut item = CompletionItem {
+        label: name.clone(),
+        kind: Some(CompletionItemKind::FUNCTION),
+        detail: Some(format!("{name}({})", params.join(", "))),
+        insert_text: Some(name.clone()),
+        ..Default::default()
+    };
+
+    if call {
+        let placeholders: Vec<String> = params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("${{{}:{p}}}", i + 1))
+            .collect();
+
+        item.insert_text = Some(format!("{name}({})$0", placeholders.join(", ")));
+        item.insert_text_format = Some(InsertTextFormat::SNIPPET);
+    }
+
+    item
+}
+
+/// 后缀补全的模板：`(标签, 展开后的代码, 说明)`，`{expr}` 处填入接收者
+const POSTFIX_TEMPLATES: &[(&str, &str, &str)] = &[
+    ("if", "if {expr} {\n\t$0\n}", "if expr {}"),
+    ("while", "while {expr} {\n\t$0\n}", "while expr {}"),
+    ("not", "!{expr}$0", "!expr"),
+    ("let", "let ${1:name} = {expr};$0", "let name = expr;"),
+    ("return", "return {expr};$0", "return expr;"),
+];
+
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
+/// 光标前是 `expr.` 加正在输入的名字时，`expr` 的字节范围和 `.` 的下标。
+/// `expr` 可以是解析得到的变量、字面量、调用 `f(..)` 或括号表达式，不处理 `a.b.` 这样的链
+fn postfix_receiver(
+    src: &str,
+    tokens: &[SyntaxToken],
+    outline: &Outline,
+    offset: usize,
+    prefix: &str,
+) -> Option<((usize, usize), usize)> {
+    let dot_end = offset - prefix.len();
+    let dot = tokens
+        .iter()
+        .position(|t| t.end == dot_end && t.is(src, "."))?;
+    let last = dot.checked_sub(1)?;
+
+    let mut first = last;
+    let tok = tokens[last];
+    match tok.kind {
+        TokenKind::Number | TokenKind::Str => {}
+        TokenKind::Keyword if matches!(tok.text(src), "true" | "false") => {}
+        TokenKind::Ident => {
+            let decl = outline.resolve(tok.text(src), tok.start)?;
+            if matches!(
+                outline.symbols[decl].kind,
+                SymbolKind::Function | SymbolKind::Struct
+            ) {
+                return None;
+            }
+        }
+        _ if tok.is(src, ")") => {
+            let mut depth = 0usize;
+            first = (0..=last).rev().find(|k| {
+                match tokens[*k].text(src) {
+                    ")" | "]" | "}" => depth += 1,
+                    "(" | "[" | "{" => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })?;
+            if first > 0 && tokens[first - 1].kind == TokenKind::Ident {
+                first -= 1;
+            }
+        }
+        _ => return None,
+    }
+
+    let chained = first
+        .checked_sub(1)
+        .is_some_and(|k| matches!(tokens[k].text(src), "." | "::"));
+    (!chained).then_some(((tokens[first].start, tok.end), dot))
+}
+
+/// `expr.if` 等后缀补全：主编辑把 `.` 之后输入的名字换成展开的代码，
+/// 附加编辑删掉原来的 `expr.`
+fn postfix_items(
+    doc: &Document,
+    receiver: (usize, usize),
+    dot_end: usize,
+    offset: usize,
+) -> Vec<CompletionItem> {
+    let expr = escape_snippet(&doc.text[receiver.0..receiver.1]);
+    let remove = TextEdit {
+        range: doc.lines.range((receiver.0, dot_end)),
+        new_text: String::new(),
+    };
+
+    POSTFIX_TEMPLATES
+        .iter()
+        .enumerate()
+        .map(|(i, (label, template, detail))| CompletionItem {
+            label: label.to_string(),
+            kind: Some(CompletionItemKind::SNIPPET),
+            detail: Some(detail.to_string()),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range: doc.lines.range((dot_end, offset)),
+                new_text: template.replace("{expr}", &expr),
+            })),
+            additional_text_edits: Some(vec![remove.clone()]),
+            sort_text: Some(format!("{i}")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// `tokens` 末尾所在的、还没有闭合的 `<` 的下标；遇到括号或语句边界时为 None
+fn open_angle(src: &str, tokens: &[SyntaxToken]) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, t) in tokens.iter().enumerate().rev() {
+        match t.text(src) {
+            ">" => depth += 1,
+            "<" if depth == 0 => return Some(i),
+            "<" => depth -= 1,
+            "(" | ")" | "[" | "]" | "{" | "}" | ";" | "=" => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 前面的 `let x:`、`func f(a:`、`struct S { f:`、`->` 或类型实参列表说明光标处需要一个类型
+fn type_position(src: &str, tokens: &[SyntaxToken], offset: usize) -> bool {
+    let before = &tokens[..tokens.partition_point(|t| t.end <= offset)];
+    // 正在输入的类型名本身不算
+    let before = match before.last() {
+        Some(t) if t.kind == TokenKind::Ident && t.end == offset => &before[..before.len() - 1],
+        _ => before,
+    };
+
+    let Some((last, rest)) = before.split_last() else {
+        return false;
+    };
+    if last.is(src, "->") {
+        return true;
+    }
+    // 类型实参列表 `List<|>`、`Map<K, |>`：`<` 前面的名字本身在类型位置上
+    if last.is(src, "<") || last.is(src, ",") {
+        return open_angle(src, before).is_some_and(|k| {
+            k.checked_sub(1)
+                .map(|name| before[name])
+                .is_some_and(|name| {
+                    name.kind == TokenKind::Ident && type_position(src, tokens, name.start)
+                })
+        });
+    }
+    if !last.is(src, ":") {
+        return false;
+    }
+
+    let text = |i: Option<usize>| i.and_then(|i| rest.get(i)).map_or("", |t| t.text(src));
+    let n = rest.len();
+    if matches!(text(n.checked_sub(2)), "let" | "mut" | "const") {
+        return true;
+    }
+
+    // 所在的括号属于函数参数列表或结构体定义
+    let mut depth = 0usize;
+    for (i, t) in rest.iter().enumerate().rev() {
+        match t.text(src) {
+            ")" | "]" | "}" => depth += 1,
+            "(" | "[" | "{" if depth > 0 => depth -= 1,
+            open @ ("(" | "{") => {
+                let keyword = if open == "(" { "func" } else { "struct" };
+                return text(i.checked_sub(2)) == keyword;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// 光标在结构体字面量 `S { .. }` 中字段名的位置时，给出尚未写出的字段（按声明顺序）。
+/// 不在这种位置时返回 None；字段都已写出时返回空列表
+fn field_items(
+    src: &str,
+    tokens: &[SyntaxToken],
+    outline: &Outline,
+    offset: usize,
+    prefix: &str,
+) -> Option<Vec<CompletionItem>> {
+    let n = tokens.partition_point(|t| t.end <= offset);
+    // 正在输入的字段名本身不算
+    let before = match tokens[..n].last() {
+        Some(t) if t.kind == TokenKind::Ident && t.end == offset => n - 1,
+        _ => n,
+    };
+    if !tokens[..before]
+        .last()
+        .is_some_and(|t| t.is(src, "{") || t.is(src, ","))
+    {
+        return None;
+    }
+
+    // 最内层未闭合的括号必须是 `{`
+    let mut depth = 0usize;
+    let mut open = None;
+    for (i, t) in tokens[..before].iter().enumerate().rev() {
+        match t.text(src) {
+            ")" | "]" | "}" => depth += 1,
+            "(" | "[" | "{" if depth > 0 => depth -= 1,
+            "{" => {
+                open = Some(i);
+                break;
+            }
+            "(" | "[" => return None,
+            _ => {}
+        }
+    }
+    let open = open?;
+
+    let name = tokens
+        .get(open.checked_sub(1)?)
+        .filter(|t| t.kind == TokenKind::Ident)?;
+    let is_decl = open
+        .checked_sub(2)
+        .is_some_and(|i| tokens[i].is(src, "struct"));
+    let target = outline.resolve(name.text(src), name.start)?;
+    if is_decl || outline.symbols[target].kind != SymbolKind::Struct {
+        return None;
+    }
+
+    // 字面量中已经写出的 `name:`
+    let close = outline::matching(src, tokens, open).unwrap_or(tokens.len());
+    let mut present = vec![];
+    let mut depth = 0usize;
+    for i in open + 1..close {
+        match tokens[i].text(src) {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            field
+                if depth == 0
+                    && matches!(tokens[i - 1].text(src), "{" | ",")
+                    && tokens.get(i + 1).is_some_and(|t| t.is(src, ":")) =>
+            {
+                present.push(field);
+            }
+            _ => {}
+        }
+    }
+
+    let mut fields: Vec<_> = outline
+        .children(target)
+        .filter(|f| f.kind == SymbolKind::Field && !present.contains(&f.name.as_str()))
+        .filter(|f| f.name.starts_with(prefix))
+        .collect();
+    fields.sort_by_key(|f| f.name_span.0);
+
+    Some(
+        fields
+            .into_iter()
+            .map(|f| CompletionItem {
+                label: f.name.clone(),
+                kind: Some(CompletionItemKind::FIELD),
+                detail: f.ty.clone(),
+                insert_text: Some(f.name.clone()),
+                ..Default::default()
+            })
+            .collect(),
+    )
+}
+
+/// 光标处需要的值的类型：`return` 之后是所在函数声明的返回类型，
+/// `if`、`while`、`!`、`&&`、`||` 之后是 `bool`，`let x: T =` 之后是 `T`
+fn expected_type<'a>(
+    src: &str,
+    tokens: &[SyntaxToken],
+    outline: &'a Outline,
+    offset: usize,
+) -> Option<&'a str> {
+    let n = tokens.partition_point(|t| t.end <= offset);
+    // 正在输入的名字本身不算
+    let before = match tokens[..n].last() {
+        Some(t) if t.kind == TokenKind::Ident && t.end == offset => n - 1,
+        _ => n,
+    };
+    let prev = before.checked_sub(1)?;
+
+    match tokens[prev].text(src) {
+        "if" | "while" | "!" | "&&" | "||" => Some("bool"),
+        "return" => outline.symbols[outline.enclosing_function(offset)?]
+            .ty
+            .as_deref()
+            .filter(|ty| *ty != "()"),
+        "=" => {
+            // 所在语句以 `let` / `const` 开头时是初始值，取声明的类型注解
+            let start = tokens[..prev]
+                .iter()
+                .rposition(|t| matches!(t.text(src), ";" | "{" | "}"))
+                .map_or(0, |i| i + 1);
+            if !matches!(tokens[start].text(src), "let" | "const") {
+                return None;
+            }
+            let name = tokens[start + 1..prev]
+                .iter()
+                .find(|t| t.kind == TokenKind::Ident)?;
+            outline
+                .symbols
+                .iter()
+                .find(|s| s.name_span.0 == name.start)?
+                .ty
+                .as_deref()
+        }
+        _ => None,
+    }
+}
+
+/// 按声明区分补全项的种类：参数与局部变量不同，文件作用域中不可变的绑定算作常量。
+/// 没有声明（内置、导入）的名字按类型判断是否为函数
+fn item_kind(outline: &Outline, decl: Option<usize>, ty: Option<&str>) -> CompletionItemKind {
+    let Some(sym) = decl.map(|it| &outline.symbols[it]) else {
+        return match ty {
+            Some(ty) if ty.starts_with("func") => CompletionItemKind::FUNCTION,
+            _ => CompletionItemKind::VARIABLE,
+        };
+    };
+
+    match sym.kind {
+        SymbolKind::Function => CompletionItemKind::FUNCTION,
+        SymbolKind::Struct => CompletionItemKind::STRUCT,
+        SymbolKind::Field => CompletionItemKind::FIELD,
+        SymbolKind::Parameter => CompletionItemKind::VALUE,
+        SymbolKind::Constant => CompletionItemKind::CONSTANT,
+        SymbolKind::Variable if sym.scope == 0 && !sym.mutable => CompletionItemKind::CONSTANT,
+        SymbolKind::Variable => CompletionItemKind::VARIABLE,
+    }
+}
+
+/// 其他文件中声明的名字的排序等级
+const RANK_OTHER_FILE: usize = 8;
+/// 内置名字的排序等级
+const RANK_BUILTIN: usize = 9;
+
+/// 名字按声明位置排序的等级，小的在前：光标所在作用域的声明为 1，每往外一层加 1，
+/// 文件作用域的声明为 7，之后是其他文件的和内置的名字。0 留给字面量
+fn scope_rank(outline: &Outline, decl: usize, offset: usize) -> usize {
+    let target = outline.symbols[decl].scope;
+    if target == 0 {
+        return 7;
+    }
+
+    let mut scope = Some(outline.scope_at(offset));
+    let mut distance = 1;
+    while let Some(current) = scope.filter(|it| *it != target) {
+        scope = outline.scopes[current].parent;
+        distance += 1;
+    }
+    distance.min(6)
+}
+
+/// 标了 `@deprecated` 的符号删除线显示，排在所有其他名字之后
+fn mark_deprecated(item: &mut CompletionItem, deprecated: bool) {
+    if !deprecated {
+        return;
+    }
+    item.tags = Some(vec![CompletionItemTag::DEPRECATED]);
+    if let Some(sort) = &mut item.sort_text {
+        sort.replace_range(..1, "2");
+    }
+}
+
+/// 需要 `bool` 的位置给出 `true` / `false`，排在最前面
+fn bool_items(prefix: &str) -> Vec<CompletionItem> {
+    ["true", "false"]
+        .into_iter()
+        .filter(|it| it.starts_with(prefix))
+        .map(|it| CompletionItem {
+            label: it.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            detail: Some("bool".into()),
+            insert_text: Some(it.to_string()),
+            sort_text: Some(format!("00_{it}")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// 忽略空白比较两个类型
+fn same_type(a: &str, b: &str) -> bool {
+    a.chars()
+        .filter(|c| !c.is_whitespace())
+        .eq(b.chars().filter(|c| !c.is_whitespace()))
+}
+
+/// 限定部分对应的导入文件：`import "./std/io.ant"` 可以写作 `io::` 或 `std::io::`。
+/// 限定部分的第一段是本文件中的声明时是成员访问，不是模块
+fn qualified_module(uri: &Url, doc: &Document, qualifier: &[String], offset: usize) -> Option<Url> {
+    if doc.outline.resolve(qualifier.first()?, offset).is_some() {
+        return None;
+    }
+    let from = uri.to_file_path().ok()?;
+
+    doc.outline.imports.iter().find_map(|import| {
+        let path = import
+            .path
+            .strip_suffix(&format!(".{SOURCE_EXT}"))
+            .unwrap_or(&import.path);
+        let segments: Vec<&str> = path
+            .split('/')
+            .filter(|it| !matches!(*it, "" | "." | ".."))
+            .collect();
+
+        segments
+            .ends_with(&qualifier.iter().map(String::as_str).collect::<Vec<_>>())
+            .then(|| Url::from_file_path(resolve_import(&from, &import.path)).ok())
+            .flatten()
+    })
+}
+
+fn indexed_kind(symbol: &IndexedSymbol) -> CompletionItemKind {
+    use tower_lsp::lsp_types::SymbolKind as Lsp;
+
+    match symbol.kind {
+        Lsp::FUNCTION => CompletionItemKind::FUNCTION,
+        Lsp::STRUCT => CompletionItemKind::STRUCT,
+        Lsp::CONSTANT => CompletionItemKind::CONSTANT,
+        _ => CompletionItemKind::VARIABLE,
+    }
+}
+
+fn module_items(symbols: &[IndexedSymbol], prefix: &str) -> Vec<CompletionItem> {
+    symbols
+        .iter()
+        .filter(|s| s.name.starts_with(prefix))
+        .map(|s| CompletionItem {
+            label: s.name.clone(),
+            kind: Some(indexed_kind(s)),
+            detail: s.ty.clone(),
+            insert_text: Some(s.name.clone()),
+            tags: s.deprecated.then(|| vec![CompletionItemTag::DEPRECATED]),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// 类型名：本文件的结构体，以及类型注解中已经用到的名字
+fn type_items(outline: &Outline, prefix: &str) -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = vec![];
+    let mut push = |name: &str, kind: CompletionItemKind, detail: Option<String>| {
+        if name.starts_with(prefix) && !items.iter().any(|it| it.label == name) {
+            items.push(CompletionItem {
+                label: name.to_string(),
+                kind: Some(kind),
+                detail,
+                insert_text: Some(name.to_string()),
+                ..Default::default()
+            });
+        }
+    };
+
+    for sym in outline
+        .symbols
+        .iter()
+        .filter(|s| s.kind == SymbolKind::Struct)
+    {
+        push(
+            &sym.name,
+            CompletionItemKind::STRUCT,
+            Some(format!("struct {}", sym.name)),
+        );
+    }
+    for ty in outline.symbols.iter().filter_map(|s| s.ty.as_deref()) {
+        for tok in syntax::scan(ty)
+            .iter()
+            .filter(|t| t.kind == TokenKind::Ident)
+        {
+            let kind = if BUILTIN_TYPES.contains(&tok.text(ty)) {
+                CompletionItemKind::KEYWORD
+            } else {
+                CompletionItemKind::STRUCT
+            };
+            push(tok.text(ty), kind, None);
+        }
+    }
+    // 新文件里还没有注解，基本类型也要能补出来
+    for name in BUILTIN_TYPES {
+        push(name, CompletionItemKind::KEYWORD, None);
+    }
+
+    items
+}
+
+impl Backend {
+    pub(crate) async fn completion_items(
+        &self,
+        uri: &Url,
+        doc: &Document,
+        pos: Position,
+    ) -> Vec<CompletionItem> {
+        let (qualifier, prefix) = qualified_ident(doc, pos);
+        let offset = doc.lines.offset(pos);
+
+        let outline = &doc.outline;
+        let tokens = syntax::code_tokens(&doc.tokens);
+
+        // 类型位置只给类型名，不混入变量
+        if type_position(&doc.text, &tokens, offset) {
+            return type_items(outline, &prefix);
+        }
+        // 结构体字面量中字段名的位置只给字段
+        if let Some(items) = field_items(&doc.text, &tokens, outline, offset, &prefix) {
+            return items;
+        }
+        // 限定名只给对应模块的成员
+        if let Some(module) = qualified_module(uri, doc, &qualifier, offset) {
+            let workspace = self.workspace.read().await;
+            return module_items(workspace.symbols(&module), &prefix);
+        }
+
+        let snippets = snippet_support(&*self.client_capabilities.read().await);
+        let settings = self.settings.read().await.completion.clone();
+
+        // `expr.` 之后给后缀补全
+        if snippets && settings.postfix_snippets {
+            let receiver = postfix_receiver(&doc.text, &tokens, outline, offset, &prefix);
+            if let Some((receiver, dot)) = receiver {
+                let items = postfix_items(doc, receiver, tokens[dot].end, offset);
+                return items
+                    .into_iter()
+                    .filter(|it| it.label.starts_with(&prefix))
+                    .collect();
+            }
+        }
+
+        let table = self.completion_table(uri, doc).await;
+
+        let call = snippets
+            && match settings.call_parens {
+                CallParens::Always => true,
+                CallParens::Never => false,
+                CallParens::Smart => !doc.text[offset..].trim_start().starts_with('('),
+            };
+
+        let builtins = self.builtins();
+        let table = table.lock().unwrap();
+        // 类型表中的名字，加上分析失败时表里可能缺失的本文件函数
+        let mut names: Vec<String> = table.var_map.keys().map(|name| name.to_string()).collect();
+
+        for func in outline
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Function)
+        {
+            if !names.contains(&func.name) {
+                names.push(func.name.clone());
+            }
+        }
+
+        // 类型与光标处需要的类型相同的排在前面
+        let expected = expected_type(&doc.text, &tokens, outline, offset);
+
+        // 只在一处声明的名字，类型表中的类型就是这个声明的
+        let declared_once = |name: &str| {
+            outline
+                .symbols
+                .iter()
+                .filter(|s| s.name == name && s.kind != SymbolKind::Field)
+                .count()
+                == 1
+        };
+
+        let mut items: Vec<CompletionItem> = names
+            .into_iter()
+            .filter(|name| name.starts_with(&prefix))
+            .filter_map(|name| {
+                // 类型表不分作用域，按光标处的作用域解析名字；本文件声明过但在这里不可见的不补全
+                let decl = outline.resolve(&name, offset);
+                if decl.is_none() && outline.declares(&name) {
+                    return None;
+                }
+                let func = decl.filter(|it| outline.symbols[*it].kind == SymbolKind::Function);
+
+                // 可见的声明的类型：写出的注解，没有时只在名字无歧义时用类型表中的
+                let table_ty = || table.var_map.get(name.as_str()).map(|ty| ty.to_string());
+                let ty = match decl {
+                    Some(decl) => outline.symbols[decl]
+                        .ty
+                        .clone()
+                        .or_else(|| declared_once(&name).then(table_ty).flatten()),
+                    None => table_ty(),
+                };
+
+                let mut item = match func {
+                    Some(func) => function_item(outline, func, call),
+                    None => CompletionItem {
+                        label: name.clone(),
+                        kind: Some(item_kind(outline, decl, ty.as_deref())),
+                        detail: ty.clone(),
+                        insert_text: Some(name.clone()),
+                        ..Default::default()
+                    },
+                };
+
+                // 先按是否符合需要的类型，再按声明的远近排序
+                let matches = expected.is_none_or(|expected| {
+                    // 函数按返回类型比较
+                    let ty = match func {
+                        Some(func) => outline.symbols[func].ty.clone(),
+                        None => ty,
+                    };
+                    ty.is_some_and(|ty| same_type(&ty, expected))
+                });
+                let rank = match decl {
+                    Some(decl) => scope_rank(outline, decl, offset),
+                    None if builtins.var_map.contains_key(name.as_str()) => RANK_BUILTIN,
+                    None => RANK_OTHER_FILE,
+                };
+                item.sort_text = Some(format!("{}{rank}_{name}", usize::from(!matches)));
+                let deprecated = decl.is_some_and(|it| outline.symbols[it].is_deprecated());
+                mark_deprecated(&mut item, deprecated);
+                Some(item)
+            })
+            .collect();
+
+        if expected.is_some_and(|ty| same_type(ty, "bool")) {
+            items.splice(0..0, bool_items(&prefix));
+        }
+
+        // 同一工作区目录中其他文件导出、本文件还没有 import 的符号；import 在 resolve 时才生成
+        if !prefix.is_empty() {
+            let workspace = self.workspace.read().await;
+            let imported = workspace.imports_of(uri);
+
+            for (target, symbol) in workspace.exported(uri) {
+                if target == uri
+                    || imported.contains(target)
+                    || !symbol.name.starts_with(&prefix)
+                    || outline.declares(&symbol.name)
+                    || items.iter().any(|it| it.label == symbol.name)
+                {
+                    continue;
+                }
+
+                let data = ImportData {
+                    uri: uri.clone(),
+                    target: target.clone(),
+                };
+                let detail = match (uri.to_file_path(), target.to_file_path()) {
+                    (Ok(from), Ok(to)) => {
+                        format!("from \"{}\"", relative_import_path(&from, &to))
+                    }
+                    _ => format!("from {target}"),
+                };
+                // 类型未知，有需要的类型时排在符合的之后
+                let unmatched = usize::from(expected.is_some());
+                let mut item = CompletionItem {
+                    label: symbol.name.clone(),
+                    kind: Some(indexed_kind(symbol)),
+                    detail: Some(detail),
+                    insert_text: Some(symbol.name.clone()),
+                    sort_text: Some(format!("{unmatched}{RANK_OTHER_FILE}_{}", symbol.name)),
+                    data: serde_json::to_value(data).ok(),
+                    ..Default::default()
+                };
+                mark_deprecated(&mut item, symbol.deprecated);
+                items.push(item);
+            }
+        }
+
+        items
+    }
+
+    /// 补全用的类型表。内容与上次诊断分析时相同的直接沿用；否则分析，
+    /// 超过 `completion.analysisTimeoutMs` 时不再等待（分析在后台继续，完成后留给下次补全），
+    /// 改用上次成功分析的类型表，没有的话只有内置名字
+    async fn completion_table(&self, uri: &Url, doc: &Document) -> Arc<Mutex<TypeTable>> {
+        let hash = content_hash(&doc.text);
+        let (current, stale) = match self.type_tables.read().await.get(uri) {
+            Some((table, _, it)) if *it == hash => (Some(table.clone()), None),
+            Some((table, ..)) => (None, Some(table.clone())),
+            None => (None, None),
+        };
+        if let Some(table) = current {
+            return Arc::new(Mutex::new(table));
+        }
+
+        let timeout = self.settings.read().await.completion.analysis_timeout_ms;
+        let analyzed = match self.completion_analysis(uri, doc, hash) {
+            Some(mut analysis) => {
+                let finished = analysis.wait_for(Option::is_some);
+                match tokio::time::timeout(Duration::from_millis(timeout), finished).await {
+                    Ok(Ok(table)) => table.clone(),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+        if let Some(table) = analyzed {
+            return Arc::new(Mutex::new(table));
+        }
+
+        let message = format!("analysis of {uri} not finished within {timeout}ms");
+        self.log_trace(message, String::new).await;
+        Arc::new(Mutex::new(stale.unwrap_or_else(|| self.builtins().clone())))
+    }
+
+    /// 内容哈希为 `hash` 的分析：已有的（进行中或已完成）直接沿用，否则发起一个。
+    /// 同一文档旧内容的分析还在进行时不再发起，返回 None
+    fn completion_analysis(
+        &self,
+        uri: &Url,
+        doc: &Document,
+        hash: u64,
+    ) -> Option<watch::Receiver<Option<TypeTable>>> {
+        let mut analyses = self.completion_analyses.lock().unwrap();
+        if let Some((it, analysis)) = analyses.get(uri) {
+            if *it == hash {
+                return Some(analysis.clone());
+            }
+            // 发送端还在说明分析没结束（分析出错时发送端直接丢弃）
+            if analysis.borrow().is_none() && analysis.has_changed().is_ok() {
+                return None;
+            }
+        }
+
+        let (tx, rx) = watch::channel(None);
+        let (task_doc, task_uri, builtins) = (doc.clone(), uri.clone(), self.builtins().clone());
+        tokio::task::spawn_blocking(move || {
+            let table = analyze(&task_doc, &task_uri, Some(&builtins), true).0;
+            let table = table.lock().unwrap().clone();
+            tx.send_replace(Some(table));
+        });
+        analyses.insert(uri.clone(), (hash, rx.clone()));
+        Some(rx)
+    }
+
+    /// 补全其他文件中的符号时，在 import 区域加上对应的 import
+    pub(crate) async fn resolve_completion(&self, mut item: CompletionItem) -> CompletionItem {
+        let Some(data) = item
+            .data
+            .clone()
+            .and_then(|it| serde_json::from_value::<ImportData>(it).ok())
+        else {
+            return item;
+        };
+
+        let docs = self.documents.read().await;
+        let Some(doc) = docs.get(&data.uri) else {
+            return item;
+        };
+        let (Ok(from), Ok(to)) = (data.uri.to_file_path(), data.target.to_file_path()) else {
+            return item;
+        };
+
+        let path = relative_import_path(&from, &to);
+        if doc.outline.imports.iter().any(|it| it.path == path) {
+            return item;
+        }
+
+        let insert_at = import_position(doc);
+        item.additional_text_edits = Some(vec![TextEdit {
+            range: Range::new(insert_at, insert_at),
+            new_text: format!("import \"{path}\";\n"),
+        }]);
+        item
+    }
+}
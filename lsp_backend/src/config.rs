@@ -0,0 +1,486 @@
+//! 用户配置（`typedAnt.*`）
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+use tower_lsp::lsp_types::{
+    ConfigurationItem, Diagnostic, DiagnosticSeverity, MessageType, NumberOrString, Registration,
+};
+
+use crate::Backend;
+use crate::glob::glob_matches;
+
+pub(crate) const SECTION: &str = "typedAnt";
+/// 工作区根目录下的项目配置文件，键与 LSP 配置相同
+pub(crate) const CONFIG_FILE: &str = "typedant.toml";
+
+/// 生效的配置由两个来源合并而来：项目中的 `typedant.toml` 和客户端发来的配置。
+/// 同一项两边都给出时以客户端为准，这样个人设置可以覆盖项目提交的默认值
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub diagnostics: DiagnosticsSettings,
+    pub completion: CompletionSettings,
+    pub format: FormatSettings,
+    pub files: FileSettings,
+    pub workspace: WorkspaceSettings,
+    /// 超过此大小（字节）的文件不做完整分析
+    pub max_file_size_bytes: usize,
+    /// 最多缓存多少个已关闭文档的分析结果
+    pub closed_document_cache: usize,
+    /// 打开内容与上次分析时相同的文件时沿用上次的分析结果，只重跑补充检查
+    pub reuse_analysis_on_open: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            diagnostics: Default::default(),
+            completion: Default::default(),
+            format: Default::default(),
+            files: Default::default(),
+            workspace: Default::default(),
+            max_file_size_bytes: 2 * 1024 * 1024,
+            closed_document_cache: 32,
+            reuse_analysis_on_open: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiagnosticsSettings {
+    /// 要屏蔽的诊断：与诊断代码完全相同，或是诊断消息的子串
+    pub suppress: Vec<String>,
+    /// 按诊断代码改写严重程度，`off` 表示不报告该代码
+    pub severity: HashMap<String, LintLevel>,
+    /// 标出 `return` / `break` / `continue` 之后不可达的代码，默认关闭
+    pub unreachable_code: bool,
+    /// 标出 `if true`、`while false` 这类常量条件和永远不会执行的分支，默认关闭
+    pub constant_conditions: bool,
+    /// 除以字面量 0
+    pub division_by_zero: LintLevel,
+    /// `if`、`while` 条件中的赋值 `=`（多半是想写 `==`），默认为 warning
+    pub assignment_in_condition: LintLevel,
+    pub style: StyleSettings,
+    pub todo: TodoSettings,
+}
+
+impl Default for DiagnosticsSettings {
+    fn default() -> Self {
+        Self {
+            suppress: vec![],
+            severity: HashMap::new(),
+            unreachable_code: false,
+            constant_conditions: false,
+            division_by_zero: LintLevel::Error,
+            assignment_in_condition: LintLevel::Warning,
+            style: Default::default(),
+            todo: Default::default(),
+        }
+    }
+}
+
+/// 诊断的严重程度，`off` 为不报告
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    #[default]
+    Error,
+    Warning,
+    Information,
+    Hint,
+    Off,
+}
+
+impl LintLevel {
+    pub fn severity(self) -> Option<DiagnosticSeverity> {
+        match self {
+            LintLevel::Error => Some(DiagnosticSeverity::ERROR),
+            LintLevel::Warning => Some(DiagnosticSeverity::WARNING),
+            LintLevel::Information => Some(DiagnosticSeverity::INFORMATION),
+            LintLevel::Hint => Some(DiagnosticSeverity::HINT),
+            LintLevel::Off => None,
+        }
+    }
+}
+
+/// 风格检查（HINT）
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StyleSettings {
+    /// 关闭后所有风格检查都不运行
+    pub enabled: bool,
+    pub naming: bool,
+    pub redundant_parens: bool,
+    /// 与初始值类型相同的类型注解
+    pub redundant_annotations: bool,
+}
+
+impl Default for StyleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            naming: true,
+            redundant_parens: true,
+            redundant_annotations: true,
+        }
+    }
+}
+
+/// 注释中的 `TODO` 等标记，以 INFORMATION 报告，默认关闭
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TodoSettings {
+    pub enabled: bool,
+    /// 作为标记的单词，区分大小写
+    pub keywords: Vec<String>,
+}
+
+impl Default for TodoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keywords: vec!["TODO".into(), "FIXME".into(), "XXX".into()],
+        }
+    }
+}
+
+/// 要分析的文件，模式语法见 [`glob_matches`]。模式与相对工作区根目录的路径比较，
+/// 不在任何工作区目录下的文件与完整路径比较
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileSettings {
+    /// 为空时分析所有源文件
+    pub include: Vec<String>,
+    /// 优先于 `include`
+    pub exclude: Vec<String>,
+}
+
+impl FileSettings {
+    pub fn includes(&self, path: &str) -> bool {
+        let matches = |patterns: &[String]| patterns.iter().any(|it| glob_matches(it, path));
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspaceSettings {
+    pub diagnostics: WorkspaceDiagnosticsSettings,
+    pub indexing: Indexing,
+}
+
+/// 工作区索引的建立方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Indexing {
+    /// 启动时索引所有源文件
+    #[default]
+    Eager,
+    /// 只索引打开的文件和它们 import 的文件，跨文件功能只能看到这些文件
+    Lazy,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspaceDiagnosticsSettings {
+    /// 关闭文件后继续按磁盘上的内容报告它的诊断，默认关闭
+    pub closed_files: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompletionSettings {
+    pub call_parens: CallParens,
+    /// 补全前的分析超过这个时间（毫秒）时不再等待，改用上次分析的结果
+    pub analysis_timeout_ms: u64,
+    /// 在 `expr.` 之后给出 `expr.if`、`expr.let` 等后缀补全，展开成 `if expr { }` 等
+    pub postfix_snippets: bool,
+    /// 不是手动请求（如 Ctrl-Space）的补全，光标前的名字至少有这么多个字符才给出，0 为不限制
+    pub min_prefix_length: usize,
+}
+
+impl Default for CompletionSettings {
+    fn default() -> Self {
+        Self {
+            call_parens: Default::default(),
+            analysis_timeout_ms: 500,
+            postfix_snippets: true,
+            min_prefix_length: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormatSettings {
+    /// 超过此宽度（制表符按 `tab_size` 计）的行在括号内或方法链处折行
+    pub max_width: usize,
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self { max_width: 100 }
+    }
+}
+
+/// 补全函数时是否插入带参数占位符的 `(..)`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallParens {
+    Always,
+    Never,
+    /// 光标后面已经有 `(` 时不再插入
+    #[default]
+    Smart,
+}
+
+impl Settings {
+    /// 改变运行的检查或分析范围的配置（而不仅是过滤结果）
+    pub fn affects_analysis(&self, other: &Settings) -> bool {
+        self.max_file_size_bytes != other.max_file_size_bytes
+            || self.files != other.files
+            || self.diagnostics.unreachable_code != other.diagnostics.unreachable_code
+            || self.diagnostics.constant_conditions != other.diagnostics.constant_conditions
+            || self.diagnostics.division_by_zero != other.diagnostics.division_by_zero
+            || self.diagnostics.assignment_in_condition != other.diagnostics.assignment_in_condition
+            || self.diagnostics.style != other.diagnostics.style
+            || self.diagnostics.todo != other.diagnostics.todo
+    }
+
+    /// 兼容 `{ "typedAnt": { .. } }` 和直接给出本节内容两种形式，无效的项使用默认值
+    pub fn from_value(value: &Value) -> Self {
+        Self::parse(value).0
+    }
+
+    /// 同 [`Settings::from_value`]，另外返回被忽略的无效项（`typedAnt.` 下的路径和原因）。
+    /// 一项无效不影响其他项
+    pub fn parse(value: &Value) -> (Self, Vec<String>) {
+        let section = value.get(SECTION).unwrap_or(value);
+        let check = |value: &Value| serde_json::from_value::<Settings>(value.clone()).map(|_| ());
+
+        let mut errors = vec![];
+        let mut kept = Value::Object(Default::default());
+        match section {
+            Value::Null => {}
+            Value::Object(_) => keep_valid(&mut kept, &mut vec![], section, &check, &mut errors),
+            _ => errors.push(format!("{SECTION}: expected an object")),
+        }
+
+        let settings = serde_json::from_value(kept).unwrap_or_default();
+        (settings, errors)
+    }
+}
+
+/// 把 `value` 的各项逐个放进 `kept` 的 `path` 处，放进去后解析失败的项丢弃并记下原因
+fn keep_valid(
+    kept: &mut Value,
+    path: &mut Vec<String>,
+    value: &Value,
+    check: &impl Fn(&Value) -> serde_json::Result<()>,
+    errors: &mut Vec<String>,
+) {
+    if let Some(map) = value.as_object().filter(|it| !it.is_empty()) {
+        for (key, value) in map {
+            path.push(key.clone());
+            keep_valid(kept, path, value, check, errors);
+            path.pop();
+        }
+        return;
+    }
+
+    // 从第一个新建的层级起整个去掉，不留下空对象
+    let mut node = &mut *kept;
+    let mut created = None;
+    for (depth, key) in path.iter().enumerate() {
+        let Value::Object(map) = node else {
+            return;
+        };
+        if !map.contains_key(key) {
+            created.get_or_insert(depth);
+            map.insert(key.clone(), Value::Object(Default::default()));
+        }
+        node = map.get_mut(key).unwrap();
+    }
+    *node = value.clone();
+
+    if let Err(err) = check(kept) {
+        let depth = created.unwrap_or(path.len() - 1);
+        let mut node = &mut *kept;
+        for key in &path[..depth] {
+            node = node.get_mut(key).unwrap();
+        }
+        if let Value::Object(map) = node {
+            map.remove(&path[depth]);
+        }
+        errors.push(format!("{SECTION}.{}: {err}", path.join(".")));
+    }
+}
+
+/// 配置的原始来源，见 [`Settings`]
+#[derive(Debug, Default)]
+pub struct ConfigSources {
+    pub file: Value,
+    pub client: Value,
+}
+
+impl ConfigSources {
+    /// 合并后的配置和其中被忽略的无效项
+    pub fn settings(&self) -> (Settings, Vec<String>) {
+        let section = |value: &Value| value.get(SECTION).unwrap_or(value).clone();
+
+        let mut merged = section(&self.file);
+        merge(&mut merged, section(&self.client));
+        Settings::parse(&merged)
+    }
+}
+
+/// 把 `over` 合并进 `base`：对象逐键合并，其他值整体覆盖
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (_, Value::Null) => {}
+        (base, over) => *base = over,
+    }
+}
+
+fn code_of(diag: &Diagnostic) -> Option<String> {
+    match &diag.code {
+        Some(NumberOrString::String(code)) => Some(code.clone()),
+        Some(NumberOrString::Number(code)) => Some(code.to_string()),
+        None => None,
+    }
+}
+
+impl DiagnosticsSettings {
+    pub fn is_suppressed(&self, diag: &Diagnostic) -> bool {
+        let code = code_of(diag);
+
+        self.suppress
+            .iter()
+            .filter(|it| !it.is_empty())
+            .any(|it| code.as_ref() == Some(it) || diag.message.contains(it.as_str()))
+    }
+
+    /// 去掉屏蔽的诊断，并按 `severity` 改写严重程度
+    pub fn filter(&self, diagnostics: &[Diagnostic]) -> Vec<Diagnostic> {
+        diagnostics
+            .iter()
+            .filter(|it| !self.is_suppressed(it))
+            .filter_map(|it| {
+                let level = code_of(it).and_then(|code| self.severity.get(&code).copied());
+                let mut diag = it.clone();
+                if let Some(level) = level {
+                    diag.severity = Some(level.severity()?);
+                }
+                Some(diag)
+            })
+            .collect()
+    }
+}
+
+impl Backend {
+    /// 重新读取第一个含有 `typedant.toml` 的工作区根目录下的配置文件。
+    /// 没有配置文件时视为空；解析失败时保留之前的内容并提示。
+    /// 无论如何都重新应用配置，客户端的配置不会因为配置文件出错而不生效
+    pub(crate) async fn reload_config_file(&self) {
+        let roots: Vec<_> = self.workspace.read().await.roots().cloned().collect();
+        let path = roots
+            .iter()
+            .map(|root| root.join(CONFIG_FILE))
+            .find(|path| path.is_file());
+
+        let value = match path {
+            None => Some(Value::Null),
+            Some(path) => {
+                let parsed = std::fs::read_to_string(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| toml::from_str(&text).map_err(|err| err.to_string()));
+
+                match parsed {
+                    Ok(value) => Some(value),
+                    Err(err) => {
+                        self.client
+                            .show_message(
+                                MessageType::WARNING,
+                                format!("cannot load {}: {err}", path.display()),
+                            )
+                            .await;
+                        None
+                    }
+                }
+            }
+        };
+
+        if let Some(value) = value {
+            self.config_sources.write().await.file = value;
+        }
+        self.apply_settings().await;
+    }
+
+    /// 客户端支持 `workspace/configuration` 时拉取本节配置，返回是否取到了；
+    /// 只更新配置来源，由调用方决定何时应用
+    pub(crate) async fn pull_client_config(&self) -> bool {
+        let supported = self
+            .client_capabilities
+            .read()
+            .await
+            .workspace
+            .as_ref()
+            .and_then(|it| it.configuration)
+            .unwrap_or(false);
+        if !supported {
+            return false;
+        }
+
+        let item = ConfigurationItem {
+            scope_uri: None,
+            section: Some(SECTION.into()),
+        };
+        match self.client.configuration(vec![item]).await {
+            Ok(values) => {
+                let value = values.into_iter().next().unwrap_or_default();
+                self.config_sources.write().await.client = value;
+                true
+            }
+            Err(err) => {
+                let message = format!("cannot pull configuration: {err}");
+                self.client.log_message(MessageType::WARNING, message).await;
+                false
+            }
+        }
+    }
+
+    /// 客户端支持动态注册时，注册 `workspace/didChangeConfiguration`，
+    /// 之后配置变化时客户端会通知（通常不带内容，由服务器重新拉取）
+    pub(crate) async fn register_configuration(&self) {
+        let dynamic = self
+            .client_capabilities
+            .read()
+            .await
+            .workspace
+            .as_ref()
+            .and_then(|it| it.did_change_configuration.as_ref())
+            .and_then(|it| it.dynamic_registration)
+            .unwrap_or(false);
+        if !dynamic {
+            return;
+        }
+
+        let registration = Registration {
+            id: "typedAnt/configuration".into(),
+            method: "workspace/didChangeConfiguration".into(),
+            register_options: Some(serde_json::json!({ "section": SECTION })),
+        };
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            let message = format!("cannot register for configuration changes: {err}");
+            self.client.log_message(MessageType::WARNING, message).await;
+        }
+    }
+}
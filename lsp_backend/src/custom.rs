@@ -0,0 +1,192 @@
+//! 自定义请求、通知（`typedAnt/*`）和命令（`typedAnt.*`）
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
+use tower_lsp::lsp_types::notification::Notification;
+use tower_lsp::lsp_types::{
+    Diagnostic, FormattingOptions, Location, Position, Range, TextDocumentPositionParams, Url,
+};
+
+use crate::syntax_tree::{self, SyntaxTree};
+use crate::{Backend, check_file, run_passes, source_name};
+
+pub(crate) const TOGGLE_DIAGNOSTICS: &str = "typedAnt.toggleDiagnostics";
+pub(crate) const NEXT_DIAGNOSTIC: &str = "typedAnt.nextDiagnostic";
+pub(crate) const PREV_DIAGNOSTIC: &str = "typedAnt.prevDiagnostic";
+pub(crate) const FIX_ALL: &str = "typedAnt.fixAll";
+
+/// `typedAnt/status`：服务器状态变化时发送给客户端
+pub enum StatusNotification {}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusParams {
+    pub diagnostics_enabled: bool,
+}
+
+impl Notification for StatusNotification {
+    type Params = StatusParams;
+    const METHOD: &'static str = "typedAnt/status";
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyzeFileParams {
+    pub uri: Url,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FormatTextParams {
+    pub text: String,
+    pub options: FormattingOptions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AstParams {
+    pub uri: Url,
+}
+
+impl Backend {
+    /// `typedAnt/analyzeFile`：分析磁盘上的文件并返回诊断，文件无需打开
+    pub async fn analyze_file(&self, params: AnalyzeFileParams) -> Result<Vec<Diagnostic>> {
+        match params.uri.to_file_path() {
+            Ok(path) => Ok(check_file(&path)),
+            Err(_) => Err(Error::invalid_params(format!(
+                "not a file uri: {}",
+                params.uri
+            ))),
+        }
+    }
+
+    /// `typedAnt/formatText`：格式化请求中给出的源码并直接返回结果。
+    /// 无法解析时返回错误，`data` 中是对应的诊断，以区别于“格式化后没有变化”
+    pub async fn format_text(&self, params: FormatTextParams) -> Result<String> {
+        let settings = self.settings.read().await.format.clone();
+        crate::format_text(&params.text, &params.options, &settings).map_err(|diag| Error {
+            code: ErrorCode::InvalidParams,
+            message: format!(
+                "cannot format: {} at {}:{}",
+                diag.message,
+                diag.range.start.line + 1,
+                diag.range.start.character + 1
+            )
+            .into(),
+            data: serde_json::to_value(&diag).ok(),
+        })
+    }
+
+    /// `typedAnt/ast`：文档的语法树，未打开的文档读取磁盘上的文件。
+    /// 无法解析时返回错误，`data` 中是对应的诊断
+    pub async fn syntax_tree(&self, params: AstParams) -> Result<SyntaxTree> {
+        let uri = params.uri;
+        let open = self.documents.read().await.get(&uri).cloned();
+        let doc = match open {
+            Some(doc) => doc,
+            None => {
+                let text = uri
+                    .to_file_path()
+                    .ok()
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+                    .ok_or_else(|| Error::invalid_params(format!("cannot read {uri}")))?;
+                self.new_document(text)
+            }
+        };
+
+        run_passes(&doc, source_name(&uri), None).map_err(|diag| Error {
+            code: ErrorCode::InvalidParams,
+            message: format!("cannot parse {uri}: {}", diag.message).into(),
+            data: serde_json::to_value(&diag).ok(),
+        })?;
+
+        Ok(syntax_tree::syntax_tree(&doc))
+    }
+}
+
+impl Backend {
+    /// `typedAnt.toggleDiagnostics`：暂停时清空所有打开文件的诊断，恢复时重新分析
+    pub(crate) async fn toggle_diagnostics(&self) {
+        let paused = {
+            let mut paused = self.diagnostics_paused.write().await;
+            *paused = !*paused;
+            *paused
+        };
+
+        let docs = self.documents.read().await.clone();
+        for (uri, doc) in &docs {
+            self.check_and_publish(uri, doc).await;
+        }
+        let closed: Vec<_> = self
+            .closed_diagnostics
+            .read()
+            .await
+            .keys()
+            .cloned()
+            .collect();
+        for uri in &closed {
+            self.check_closed(uri).await;
+        }
+
+        self.client
+            .send_notification::<StatusNotification>(StatusParams {
+                diagnostics_enabled: !paused,
+            })
+            .await;
+    }
+}
+
+/// 光标之后（`forward`）或之前最近的诊断起点，到头后绕回另一端
+fn nearest(mut starts: Vec<Position>, from: Position, forward: bool) -> Option<Position> {
+    starts.sort();
+    starts.dedup();
+
+    if forward {
+        starts
+            .iter()
+            .find(|it| **it > from)
+            .or(starts.first())
+            .copied()
+    } else {
+        starts
+            .iter()
+            .rev()
+            .find(|it| **it < from)
+            .or(starts.last())
+            .copied()
+    }
+}
+
+impl Backend {
+    /// `typedAnt.nextDiagnostic` / `typedAnt.prevDiagnostic`：参数为 `TextDocumentPositionParams`，
+    /// 返回要跳转到的 `Location`，没有诊断时返回 null
+    pub(crate) async fn goto_diagnostic(
+        &self,
+        arguments: Vec<serde_json::Value>,
+        forward: bool,
+    ) -> Result<Option<serde_json::Value>> {
+        let params: TextDocumentPositionParams = arguments
+            .into_iter()
+            .next()
+            .and_then(|it| serde_json::from_value(it).ok())
+            .ok_or_else(|| {
+                tower_lsp::jsonrpc::Error::invalid_params("expected TextDocumentPositionParams")
+            })?;
+        let uri = params.text_document.uri;
+
+        let starts = match self.diagnostics.read().await.get(&uri) {
+            Some(diagnostics) => {
+                let settings = self.settings.read().await;
+                settings
+                    .diagnostics
+                    .filter(diagnostics)
+                    .iter()
+                    .map(|it| it.range.start)
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        let location = nearest(starts, params.position, forward)
+            .map(|pos| Location::new(uri, Range::new(pos, pos)));
+
+        Ok(location.and_then(|it| serde_json::to_value(it).ok()))
+    }
+}
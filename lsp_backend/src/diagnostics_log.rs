@@ -0,0 +1,70 @@
+//! 调试用的诊断日志：环境变量 `TYPED_ANT_DIAGNOSTICS_LOG` 给出文件路径时，
+//! 每次发布的诊断都以一行 JSON 追加到该文件，用来复现“诊断位置不对”“诊断闪烁”一类问题。
+//! 没有设置时不写任何东西
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+use tower_lsp::lsp_types::{Diagnostic, Url};
+
+pub(crate) const ENV: &str = "TYPED_ANT_DIAGNOSTICS_LOG";
+/// 超过此大小时把当前文件改名为 `<path>.1`（覆盖更早的），重新开始写
+const MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct DiagnosticsLog {
+    path: PathBuf,
+    /// 保证轮转和追加不会交错
+    lock: Mutex<()>,
+}
+
+impl DiagnosticsLog {
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var_os(ENV).filter(|it| !it.is_empty())?;
+        Some(Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// 记录一次发布；`elapsed` 是得到这组诊断的分析耗时，只重新过滤时为 None。
+    /// 写入失败不影响正常工作
+    pub fn record(
+        &self,
+        uri: &Url,
+        version: Option<i32>,
+        elapsed: Option<Duration>,
+        diagnostics: &[Diagnostic],
+    ) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let line = json!({
+            "timestampMs": timestamp,
+            "uri": uri,
+            "version": version,
+            "analysisMs": elapsed.map(|it| it.as_secs_f64() * 1000.0),
+            "diagnostics": diagnostics,
+        });
+
+        let _guard = self.lock.lock().unwrap_or_else(|it| it.into_inner());
+        if fs::metadata(&self.path).is_ok_and(|it| it.len() > MAX_BYTES) {
+            let mut rotated = self.path.clone().into_os_string();
+            rotated.push(".1");
+            let _ = fs::rename(&self.path, rotated);
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path);
+        if let Ok(mut file) = file {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use tower_lsp::lsp_types::{PositionEncodingKind, TextDocumentContentChangeEvent};
+
+use crate::line_index::LineIndex;
+use crate::outline::{self, Outline};
+use crate::syntax::{self, SyntaxToken};
+
+/// 打开的文档，以及只在文本变化时重建的行索引、token 和大纲
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub text: String,
+    pub lines: LineIndex,
+    /// 包括注释的全部 token
+    pub(crate) tokens: Vec<SyntaxToken>,
+    pub(crate) outline: Outline,
+}
+
+impl Document {
+    /// 位置按 UTF-16 计的文档
+    pub fn new(text: String) -> Self {
+        Self::with_encoding(text, &PositionEncodingKind::UTF16)
+    }
+
+    pub fn with_encoding(text: String, encoding: &PositionEncodingKind) -> Self {
+        let lines = LineIndex::with_encoding(&text, encoding);
+        let tokens = syntax::scan(&text);
+        let outline = outline::outline(&text, &tokens);
+
+        Self {
+            text,
+            lines,
+            tokens,
+            outline,
+        }
+    }
+}
+
+/// 把一个内容修改应用到文本上，返回新的文本：没有 `range` 的是完整文本，
+/// 有 `range` 的是增量修改，列号按 `encoding` 计
+pub fn apply_change(
+    text: &str,
+    change: &TextDocumentContentChangeEvent,
+    encoding: &PositionEncodingKind,
+) -> String {
+    let Some(range) = change.range else {
+        return change.text.clone();
+    };
+
+    let lines = LineIndex::with_encoding(text, encoding);
+    let start = lines.offset(range.start);
+    let end = lines.offset(range.end).max(start);
+
+    let mut out = String::with_capacity(text.len() - (end - start) + change.text.len());
+    out.push_str(&text[..start]);
+    out.push_str(&change.text);
+    out.push_str(&text[end..]);
+    out
+}
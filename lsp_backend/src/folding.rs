@@ -0,0 +1,109 @@
+//! 折叠范围（`textDocument/foldingRange`）：代码块、多行注释和 `// region` 标记的区域
+
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::syntax::{SyntaxToken, TokenKind};
+
+/// 行注释是否是区域标记：`// region [名字]` 为开始，`// endregion` 为结束
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Marker {
+    Start,
+    End,
+}
+
+fn marker(src: &str, tok: &SyntaxToken) -> Option<Marker> {
+    let body = tok.text(src).strip_prefix("//")?.trim_start();
+    let word = body.split_whitespace().next()?;
+
+    match word.strip_prefix('#').unwrap_or(word) {
+        "region" => Some(Marker::Start),
+        "endregion" => Some(Marker::End),
+        _ => None,
+    }
+}
+
+fn fold(start_line: u32, end_line: u32, kind: Option<FoldingRangeKind>) -> FoldingRange {
+    FoldingRange {
+        start_line,
+        end_line,
+        kind,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn folding_ranges(doc: &Document) -> Vec<FoldingRange> {
+    let src = doc.text.as_str();
+    let line = |offset: usize| doc.lines.position(offset).line;
+    let mut ranges = vec![];
+
+    // 括号：折叠到闭括号的上一行，闭括号保持可见
+    let mut open = vec![];
+    for tok in doc.tokens.iter().filter(|t| t.kind == TokenKind::Punct) {
+        match tok.text(src) {
+            "{" | "(" | "[" => open.push(tok.start),
+            "}" | ")" | "]" => {
+                if let Some(start) = open.pop() {
+                    let (start, end) = (line(start), line(tok.start));
+                    if end > start + 1 {
+                        ranges.push(fold(start, end - 1, None));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 注释：多行的块注释，以及连续多行的行注释（区域标记除外）
+    let mut regions = vec![];
+    let mut run: Option<(u32, u32)> = None;
+    let flush = |run: &mut Option<(u32, u32)>, ranges: &mut Vec<FoldingRange>| {
+        if let Some((start, end)) = run.take().filter(|(start, end)| end > start) {
+            ranges.push(fold(start, end, Some(FoldingRangeKind::Comment)));
+        }
+    };
+
+    for tok in &doc.tokens {
+        let (start, end) = (line(tok.start), line(tok.end));
+        let text = tok.text(src);
+
+        if !matches!(tok.kind, TokenKind::Comment | TokenKind::DocComment) {
+            flush(&mut run, &mut ranges);
+            continue;
+        }
+        if !text.starts_with("//") {
+            flush(&mut run, &mut ranges);
+            if end > start {
+                ranges.push(fold(start, end, Some(FoldingRangeKind::Comment)));
+            }
+            continue;
+        }
+
+        match marker(src, tok) {
+            Some(Marker::Start) => {
+                flush(&mut run, &mut ranges);
+                regions.push(start);
+            }
+            Some(Marker::End) => {
+                flush(&mut run, &mut ranges);
+                // 多余的结束标记忽略
+                if let Some(open) = regions.pop() {
+                    ranges.push(fold(open, start, Some(FoldingRangeKind::Region)));
+                }
+            }
+            None => match &mut run {
+                Some((_, last)) if *last + 1 == start => *last = start,
+                _ => {
+                    flush(&mut run, &mut ranges);
+                    run = Some((start, start));
+                }
+            },
+        }
+    }
+    flush(&mut run, &mut ranges);
+
+    // 客户端在同一行只保留一个折叠，起始行相同时保留范围最大的
+    ranges.sort_by_key(|it| (it.start_line, std::cmp::Reverse(it.end_line)));
+    ranges.dedup_by_key(|it| it.start_line);
+    ranges
+}
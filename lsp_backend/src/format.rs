@@ -0,0 +1,242 @@
+//! 格式化：按括号深度重新缩进，去掉行尾空白和文件末尾多余的空行
+//!
+//! 只调整空白，不改变 token 本身；跨行的字符串和块注释内部原样保留。
+//! 超过 `maxWidth` 的行在方法链或括号内折行，折出的行仍按括号深度缩进，
+//! 所以对结果再格式化一次不会有变化。
+
+use std::ops::Range;
+
+use tower_lsp::lsp_types::FormattingOptions;
+
+use crate::config::FormatSettings;
+use crate::document::Document;
+use crate::syntax::{SyntaxToken, TokenKind};
+
+fn opens(src: &str, tok: &SyntaxToken) -> bool {
+    tok.kind == TokenKind::Punct && matches!(tok.text(src), "{" | "(" | "[")
+}
+
+fn closes(src: &str, tok: &SyntaxToken) -> bool {
+    tok.kind == TokenKind::Punct && matches!(tok.text(src), "}" | ")" | "]")
+}
+
+struct Layout<'a> {
+    src: &'a str,
+    tokens: &'a [SyntaxToken],
+    unit: String,
+    tab_size: usize,
+    max_width: usize,
+}
+
+impl Layout<'_> {
+    fn step(&self, depth: usize, tok: &SyntaxToken) -> usize {
+        if opens(self.src, tok) {
+            depth + 1
+        } else if closes(self.src, tok) {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        }
+    }
+
+    /// 以 `tokens[first]` 开头、行首括号深度为 `depth` 的行的缩进层数；
+    /// 以 `.` 开头的行是方法链的延续，多缩进一层
+    fn level(&self, first: usize, depth: usize) -> usize {
+        let Some(tok) = self.tokens.get(first) else {
+            return depth;
+        };
+        let level = if closes(self.src, tok) {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+        level + usize::from(tok.kind == TokenKind::Punct && tok.is(self.src, "."))
+    }
+
+    fn width(&self, line: &str) -> usize {
+        line.chars()
+            .map(|c| if c == '\t' { self.tab_size } else { 1 })
+            .sum()
+    }
+
+    /// 把 `tokens[range]` 排成一行，过长时拆开后递归排列各部分
+    fn emit(&self, range: Range<usize>, depth: usize, out: &mut Vec<String>) {
+        let content = &self.src[self.tokens[range.start].start..self.tokens[range.end - 1].end];
+        let line = format!(
+            "{}{content}",
+            self.unit.repeat(self.level(range.start, depth))
+        );
+
+        let pieces = if self.width(&line) > self.max_width {
+            self.split(range)
+        } else {
+            None
+        };
+        let Some(pieces) = pieces else {
+            out.push(line);
+            return;
+        };
+
+        let mut depth = depth;
+        for piece in pieces {
+            self.emit(piece.clone(), depth, out);
+            depth = self.tokens[piece]
+                .iter()
+                .fold(depth, |d, t| self.step(d, t));
+        }
+    }
+
+    /// 拆分一行：优先在方法链的 `.` 之前断开，否则在最外层最长的一对括号内按逗号逐项断开，
+    /// 代码块 `{ .. }` 没有逗号时整体放到下一行
+    fn split(&self, range: Range<usize>) -> Option<Vec<Range<usize>>> {
+        let (src, tokens) = (self.src, self.tokens);
+
+        // 每个 token 之前相对行首的括号深度
+        let mut rel = 0i32;
+        let before: Vec<i32> = tokens[range.clone()]
+            .iter()
+            .map(|t| {
+                let at = rel;
+                if opens(src, t) {
+                    rel += 1;
+                } else if closes(src, t) {
+                    rel -= 1;
+                }
+                at
+            })
+            .collect();
+        let rel_at = |i: usize| before[i - range.start];
+
+        let is = |i: usize, text: &str| {
+            range.contains(&i) && tokens[i].kind == TokenKind::Punct && tokens[i].is(src, text)
+        };
+
+        let calls: Vec<usize> = (range.start + 1..range.end)
+            .filter(|&i| rel_at(i) == 0 && is(i, "."))
+            .filter(|&i| {
+                tokens
+                    .get(i + 1)
+                    .is_some_and(|t| t.kind == TokenKind::Ident)
+                    && is(i + 2, "(")
+            })
+            .collect();
+        if calls.len() >= 2 {
+            let mut bounds = vec![range.start];
+            bounds.extend(&calls);
+            bounds.push(range.end);
+            return Some(bounds.windows(2).map(|w| w[0]..w[1]).collect());
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        for open in range
+            .clone()
+            .filter(|&i| rel_at(i) == 0 && opens(src, &tokens[i]))
+        {
+            let close = (open + 1..range.end).find(|&i| rel_at(i) == 1 && closes(src, &tokens[i]));
+            let Some(close) = close else {
+                continue;
+            };
+            if close > open + 1 && best.is_none_or(|(o, c)| close - open > c - o) {
+                best = Some((open, close));
+            }
+        }
+        let (open, close) = best?;
+
+        let mut pieces = vec![range.start..open + 1];
+        let mut item = open + 1;
+        for i in open + 1..close {
+            if rel_at(i) == 1 && is(i, ",") {
+                pieces.push(item..i + 1);
+                item = i + 1;
+            }
+        }
+        if item < close {
+            pieces.push(item..close);
+        }
+        pieces.push(close..range.end);
+        Some(pieces)
+    }
+}
+
+pub(crate) fn format(
+    doc: &Document,
+    options: &FormattingOptions,
+    settings: &FormatSettings,
+) -> String {
+    let text = doc.text.as_str();
+    let tokens = &doc.tokens;
+    let eol = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let layout = Layout {
+        src: text,
+        tokens,
+        unit: if options.insert_spaces {
+            " ".repeat(options.tab_size as usize)
+        } else {
+            "\t".to_string()
+        },
+        tab_size: options.tab_size as usize,
+        max_width: settings.max_width,
+    };
+    let trim_trailing = options.trim_trailing_whitespace != Some(false);
+
+    let mut lines: Vec<String> = vec![];
+    let mut depth = 0usize;
+    // 第一个起点不早于当前行首的 token
+    let mut next = 0;
+
+    for line in 0.. {
+        let Some((start, end)) = doc.lines.line_span(line) else {
+            break;
+        };
+
+        while let Some(tok) = tokens.get(next).filter(|t| t.start < start) {
+            depth = layout.step(depth, tok);
+            next += 1;
+        }
+
+        let raw = text[start..end]
+            .strip_suffix('\r')
+            .unwrap_or(&text[start..end]);
+
+        // 行首在跨行 token 内部
+        if next > 0 && tokens[next - 1].end > start {
+            lines.push(raw.to_string());
+            continue;
+        }
+
+        let count = tokens[next..].iter().take_while(|t| t.start < end).count();
+        // 行尾在跨行 token 内部时保留行尾空白，也不折行
+        let continues = count > 0 && tokens[next + count - 1].end > end;
+
+        let content = raw.trim_start();
+        let content = if trim_trailing && !continues {
+            content.trim_end()
+        } else {
+            content
+        };
+
+        if content.is_empty() {
+            lines.push(String::new());
+            continue;
+        }
+
+        let formatted = format!("{}{content}", layout.unit.repeat(layout.level(next, depth)));
+        if !continues && count > 0 && layout.width(&formatted) > layout.max_width {
+            layout.emit(next..next + count, depth, &mut lines);
+        } else {
+            lines.push(formatted);
+        }
+    }
+
+    if options.trim_final_newlines != Some(false) {
+        while lines.last().is_some_and(|it| it.is_empty()) {
+            lines.pop();
+        }
+    }
+
+    let mut out = lines.join(eol);
+    if options.insert_final_newline != Some(false) && !out.is_empty() && !out.ends_with(eol) {
+        out.push_str(eol);
+    }
+    out
+}
@@ -0,0 +1,35 @@
+//! 文件 glob，用于配置中的 `files.include` / `files.exclude` 和命令行的 `--exclude`
+
+/// `*` 和 `?` 不跨越 `/`，`**` 匹配任意多层目录
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|i| !text[..*i].contains(&b'/'))
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, tail)| *c != b'/' && glob_match(rest, tail)),
+        [c, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(t, tail)| t == c && glob_match(rest, tail)),
+    }
+}
+
+/// 和 `.gitignore` 一样，不含 `/` 的模式匹配任意一级的名字，否则匹配整个相对路径（用 `/` 分隔）
+pub fn glob_matches(pattern: &str, relative: &str) -> bool {
+    if pattern.contains('/') {
+        glob_match(
+            pattern.trim_start_matches('/').as_bytes(),
+            relative.as_bytes(),
+        )
+    } else {
+        relative
+            .split('/')
+            .any(|name| glob_match(pattern.as_bytes(), name.as_bytes()))
+    }
+}
@@ -0,0 +1,80 @@
+use ant_type_checker::table::TypeTable;
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::outline::{Outline, SymbolKind};
+use crate::references;
+use crate::types::builtin_type;
+
+/// `func name(a: T, ..) -> R`，未写类型的参数只显示名字
+pub(crate) fn signature(outline: &Outline, func: usize) -> String {
+    let sym = &outline.symbols[func];
+    let params: Vec<String> = outline
+        .children(func)
+        .map(|p| match &p.ty {
+            Some(ty) => format!("{}: {ty}", p.name),
+            None => p.name.clone(),
+        })
+        .collect();
+
+    let ret = sym
+        .ty
+        .as_ref()
+        .map_or(String::new(), |ty| format!(" -> {ty}"));
+    format!("func {}({}){ret}", sym.name, params.join(", "))
+}
+
+/// 代码和下方的文档；Markdown 时代码放在代码块中
+fn render(kind: MarkupKind, code: &str, doc: Option<&str>) -> MarkupContent {
+    let mut value = match kind {
+        MarkupKind::Markdown => format!("```ant\n{code}\n```"),
+        MarkupKind::PlainText => code.to_string(),
+    };
+    if let Some(doc) = doc {
+        value.push_str("\n\n");
+        value.push_str(doc);
+    }
+
+    MarkupContent { kind, value }
+}
+
+/// 函数名上显示签名和文档注释；不在本文件中声明的内置名字显示类型表中的类型
+pub(crate) fn hover(
+    doc: &Document,
+    pos: Position,
+    builtins: &TypeTable,
+    markup: MarkupKind,
+) -> Option<Hover> {
+    let text = doc.text.as_str();
+    let (tokens, outline) = (&doc.tokens, &doc.outline);
+    let offset = doc.lines.offset(pos);
+
+    let ident = references::ident_at(tokens, offset)?;
+    let range = Some(doc.lines.range((ident.start, ident.end)));
+
+    let contents = match references::definition_at(text, tokens, outline, offset) {
+        Some(decl) => {
+            let sym = &outline.symbols[decl];
+            if sym.kind != SymbolKind::Function {
+                return None;
+            }
+            render(markup, &signature(outline, decl), sym.doc.as_deref())
+        }
+        None => {
+            // `a.len` 中的 `len` 是成员，不是内置名字
+            let idx = tokens.iter().position(|t| *t == ident)?;
+            if references::is_member(text, tokens, idx) {
+                return None;
+            }
+
+            let name = ident.text(text);
+            let ty = builtin_type(builtins, name)?;
+            render(markup, &format!("{name}: {ty}"), None)
+        }
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(contents),
+        range,
+    })
+}
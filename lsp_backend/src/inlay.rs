@@ -0,0 +1,83 @@
+//! 内嵌提示（`textDocument/inlayHint`）
+
+use ant_type_checker::table::TypeTable;
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::outline::{self, SymbolKind};
+use crate::syntax;
+
+/// 类型检查器显示的函数类型 `func(..) -> R` 中的返回类型
+fn return_type(ty: &str) -> Option<&str> {
+    let mut depth = 0i32;
+    let mut arrow = None;
+    for (i, c) in ty.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '-' if depth == 0 && ty[i..].starts_with("->") => arrow = Some(i),
+            _ => {}
+        }
+    }
+
+    Some(ty[arrow? + 2..].trim()).filter(|it| !it.is_empty())
+}
+
+/// 没有写返回类型的顶层函数，在参数列表的 `)` 之后提示推断出的 `-> R`；
+/// 只返回 `range` 内的提示，返回 `()` 的函数不提示
+pub(crate) fn inlay_hints(doc: &Document, table: &TypeTable, range: Range) -> Vec<InlayHint> {
+    let (src, outline) = (doc.text.as_str(), &doc.outline);
+    let tokens = syntax::code_tokens(&doc.tokens);
+    let (from, to) = (doc.lines.offset(range.start), doc.lines.offset(range.end));
+
+    let mut hints = vec![];
+    for func in outline
+        .top_level()
+        .filter(|s| s.kind == SymbolKind::Function)
+    {
+        if func.ty.is_some() {
+            continue;
+        }
+
+        let name = tokens.partition_point(|t| t.start < func.name_span.0);
+        let Some(close) = tokens
+            .get(name + 1)
+            .filter(|t| t.is(src, "("))
+            .and_then(|_| outline::matching(src, &tokens, name + 1))
+        else {
+            continue;
+        };
+        let at = tokens[close].end;
+        if at < from || at > to {
+            continue;
+        }
+
+        let Some(ty) = table
+            .var_map
+            .get(func.name.as_str())
+            .map(|ty| ty.to_string())
+        else {
+            continue;
+        };
+        let Some(ret) = return_type(&ty).filter(|it| *it != "()") else {
+            continue;
+        };
+
+        let position = doc.lines.position(at);
+        hints.push(InlayHint {
+            position,
+            label: InlayHintLabel::String(format!("-> {ret}")),
+            kind: Some(InlayHintKind::TYPE),
+            text_edits: Some(vec![TextEdit::new(
+                Range::new(position, position),
+                format!(" -> {ret}"),
+            )]),
+            tooltip: None,
+            padding_left: Some(true),
+            padding_right: None,
+            data: None,
+        });
+    }
+
+    hints
+}
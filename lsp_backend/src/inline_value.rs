@@ -0,0 +1,49 @@
+//! 调试时的内联值（`textDocument/inlineValue`）：由调试器按变量名查出运行时的值
+
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::outline::SymbolKind;
+use crate::syntax::{self, TokenKind};
+
+/// `range` 中引用变量、参数或常量的标识符；只到程序停下的位置为止，之后的代码还没有执行。
+/// 同一行上同名的只返回第一个
+pub(crate) fn inline_values(doc: &Document, range: Range, stopped: Range) -> Vec<InlineValue> {
+    let (src, outline) = (doc.text.as_str(), &doc.outline);
+    let from = doc.lines.offset(range.start);
+    let to = doc
+        .lines
+        .offset(range.end)
+        .min(doc.lines.offset(stopped.end));
+
+    let mut values = vec![];
+    let mut seen: Vec<(u32, &str)> = vec![];
+    for tok in syntax::code_tokens(&doc.tokens) {
+        if tok.kind != TokenKind::Ident || tok.start < from || tok.end > to {
+            continue;
+        }
+        let name = tok.text(src);
+        let Some(decl) = outline.resolve(name, tok.start) else {
+            continue;
+        };
+        if !matches!(
+            outline.symbols[decl].kind,
+            SymbolKind::Variable | SymbolKind::Parameter | SymbolKind::Constant
+        ) {
+            continue;
+        }
+
+        let range = doc.lines.range((tok.start, tok.end));
+        if seen.contains(&(range.start.line, name)) {
+            continue;
+        }
+        seen.push((range.start.line, name));
+
+        values.push(InlineValue::VariableLookup(InlineValueVariableLookup {
+            range,
+            variable_name: Some(name.to_string()),
+            case_sensitive_lookup: true,
+        }));
+    }
+    values
+}
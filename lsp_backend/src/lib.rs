@@ -1,4 +1,39 @@
+mod cache;
+mod call_hierarchy;
+mod capabilities;
+mod checks;
+mod code_action;
+mod completion;
+mod config;
+mod custom;
+mod diagnostics_log;
+mod document;
+mod folding;
+mod format;
+mod glob;
+mod hover;
+mod inlay;
+mod inline_value;
+mod line_index;
+mod links;
+mod moniker;
+mod outline;
+mod progress;
+mod recovery;
+mod references;
+mod render;
+mod scope_tree;
+mod semantic;
+mod style;
+mod symbols;
+mod syntax;
+mod syntax_tree;
+mod trace;
+mod types;
 mod utils;
+mod watch;
+mod workspace;
+mod workspace_symbol;
 
 use ant_lexer::Lexer;
 use ant_parser::Parser;
@@ -7,14 +42,28 @@ use ant_type_checker::TypeChecker;
 use ant_type_checker::table::TypeTable;
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use tokio::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, watch};
 
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
-use crate::utils::UTF16Len;
+use crate::cache::{AnalysisCache, ClosedDocuments, content_hash};
+use crate::checks::CheckContext;
+use crate::config::ConfigSources;
+pub use crate::config::{FileSettings, FormatSettings, Indexing, Settings};
+use crate::diagnostics_log::DiagnosticsLog;
+pub use crate::document::{Document, apply_change};
+pub use crate::glob::glob_matches;
+pub use crate::line_index::LineIndex;
+pub use crate::render::render_diagnostic;
+pub use crate::scope_tree::{ScopeTree, ScopedSymbol};
+pub use crate::symbols::SymbolInfo;
+use crate::types::TableDiff;
+use crate::workspace::WorkspaceIndex;
 
 /* =========================
  * Backend
@@ -23,83 +72,219 @@ use crate::utils::UTF16Len;
 #[derive(Debug)]
 pub struct Backend {
     pub client: Client,
-    pub documents: RwLock<HashMap<Url, String>>,
+    pub documents: RwLock<HashMap<Url, Document>>,
+    /// 打开的文档的版本号
+    pub versions: RwLock<HashMap<Url, i32>>,
+    /// 最近关闭的文档，打开的文档总在 `documents` 中
+    pub closed: RwLock<ClosedDocuments>,
+    pub workspace: RwLock<WorkspaceIndex>,
+    pub settings: RwLock<Settings>,
+    /// 合并出 `settings` 的原始配置
+    pub config_sources: RwLock<ConfigSources>,
+    /// 最近一次分析得到的诊断（未经过滤），配置变化时据此重新发布
+    pub diagnostics: RwLock<HashMap<Url, Vec<Diagnostic>>>,
+    /// 按 `workspace.diagnostics.closedFiles` 分析过的已关闭文件的诊断（未经过滤）
+    pub closed_diagnostics: RwLock<HashMap<Url, Vec<Diagnostic>>>,
+    /// 打开的文档最近一次的上游诊断，配置变化时不必重新分析
+    pub upstream: RwLock<HashMap<Url, Vec<Diagnostic>>>,
+    /// 所有分析过的文件最近一次的上游诊断，重新打开内容相同的文件时沿用
+    pub analyses: RwLock<AnalysisCache>,
+    pub client_capabilities: RwLock<ClientCapabilities>,
+    /// `initialize` 时按客户端能力协商出的服务器能力
+    pub server_capabilities: RwLock<ServerCapabilities>,
+    /// 客户端要求的跟踪级别（`initialize` 和 `$/setTrace`）
+    pub trace: RwLock<TraceValue>,
+    /// 通过 `typedAnt.toggleDiagnostics` 暂停诊断
+    pub diagnostics_paused: RwLock<bool>,
+    /// 打开的文档最近一次成功分析的类型表，以及它相对上一次的变化，
+    /// 供只需处理变化部分的功能使用；最后是分析时文档内容的哈希
+    pub type_tables: RwLock<HashMap<Url, (TypeTable, TableDiff, u64)>>,
+    /// 补全时发起的分析，每个文档最多一个在进行，键为文档内容的哈希。
+    /// 完成后结果保留在这里，内容不变时再次补全直接沿用
+    pub completion_analyses: Mutex<HashMap<Url, (u64, watch::Receiver<Option<TypeTable>>)>>,
+    /// 内置作用域，首次使用时构建，所有文档共用。
+    /// 分析总在副本上进行，多个工作区目录共用也不会互相影响
+    pub builtins: OnceLock<TypeTable>,
+    /// 设置了 `TYPED_ANT_DIAGNOSTICS_LOG` 时记录每次发布的诊断
+    pub diagnostics_log: Option<DiagnosticsLog>,
+    /// 每个文档最近一次发给客户端的诊断的哈希，内容相同时不再重复发布
+    pub published: RwLock<HashMap<Url, u64>>,
+    /// `initialize` 时协商出的位置编码，之前为 UTF-16
+    pub position_encoding: OnceLock<PositionEncodingKind>,
 }
 
-/* =========================
- * Utils
- * ========================= */
+impl Backend {
+    /// 所有状态取默认值：没有打开的文档、空的工作区索引、默认配置
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            documents: Default::default(),
+            versions: Default::default(),
+            closed: Default::default(),
+            workspace: Default::default(),
+            settings: Default::default(),
+            config_sources: Default::default(),
+            diagnostics: Default::default(),
+            closed_diagnostics: Default::default(),
+            upstream: Default::default(),
+            analyses: Default::default(),
+            client_capabilities: Default::default(),
+            server_capabilities: Default::default(),
+            trace: Default::default(),
+            diagnostics_paused: Default::default(),
+            type_tables: Default::default(),
+            completion_analyses: Default::default(),
+            builtins: Default::default(),
+            diagnostics_log: DiagnosticsLog::from_env(),
+            published: Default::default(),
+            position_encoding: Default::default(),
+        }
+    }
 
-/// 获取光标前的标识符（UTF-8 / UTF-16 安全）
-fn current_ident(text: &str, position: Position) -> String {
-    let mut line_start = 0usize;
-    let mut current_line = 0u32;
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = RwLock::new(settings);
+        self
+    }
 
-    for (i, c) in text.char_indices() {
-        if current_line == position.line {
-            break;
-        }
-        if c == '\n' {
-            current_line += 1;
-            line_start = i + 1;
-        }
+    pub(crate) fn position_encoding(&self) -> PositionEncodingKind {
+        self.position_encoding
+            .get()
+            .cloned()
+            .unwrap_or(PositionEncodingKind::UTF16)
     }
 
-    let line = &text[line_start..];
-    let mut col_bytes = 0usize;
-    let mut chars = line.chars();
+    /// 按协商出的位置编码建立文档
+    pub(crate) fn new_document(&self, text: String) -> Document {
+        Document::with_encoding(text, &self.position_encoding())
+    }
 
-    for _ in 0..position.character {
-        if let Some(c) = chars.next() {
-            col_bytes += c.len_utf8();
-        }
+    /// 把一个修改应用到已打开的文档上并替换存储的文档，返回新的文本；不重新分析。
+    /// 文档没有打开时返回 None
+    pub async fn apply_change(
+        &self,
+        uri: &Url,
+        change: &TextDocumentContentChangeEvent,
+    ) -> Option<String> {
+        let mut docs = self.documents.write().await;
+        let doc = docs.get_mut(uri)?;
+        *doc = self.new_document(apply_change(&doc.text, change, &self.position_encoding()));
+        Some(doc.text.clone())
     }
 
-    let before = &line[..col_bytes.min(line.len())];
+    /// 预先载入的文档，按打开处理（同时加入工作区索引）
+    pub fn with_documents(mut self, documents: impl IntoIterator<Item = (Url, String)>) -> Self {
+        let docs = self.documents.get_mut();
+        let index = self.workspace.get_mut();
 
-    before
-        .chars()
-        .rev()
-        .take_while(|c| c.is_alphanumeric() || *c == '_')
-        .collect::<String>()
-        .chars()
-        .rev()
-        .collect()
+        for (uri, text) in documents {
+            index.index_file(uri.clone(), &text);
+            docs.insert(uri, Document::new(text));
+        }
+        self
+    }
 }
 
-/// Token → LSP range（UTF-16）
-fn calc_token_pos(text: &str, token: &Token) -> (u32, u32) {
-    let line_text = text.lines().nth(token.line - 1).unwrap_or("");
-    let prefix: String = line_text.chars().take(token.column - 1).collect();
-
-    let start = prefix.utf16_len() as u32;
-    let end = start + token.value.utf16_len() as u32;
+/* =========================
+ * Utils
+ * ========================= */
 
-    (start, end)
+/// Token → LSP range（UTF-16）
+fn calc_token_range(doc: &Document, token: &Token) -> Range {
+    doc.lines
+        .token_range(token.line, token.column, &token.value)
 }
 
 /* =========================
  * Core analyze (不碰 client)
  * ========================= */
 
+/// 诊断代码
+pub(crate) const LEXER_ERROR: &str = "lexer-error";
+pub(crate) const PARSE_ERROR: &str = "parse-error";
+pub(crate) const TYPE_ERROR: &str = "type-error";
+pub(crate) const IO_ERROR: &str = "io-error";
+pub(crate) const FILE_TOO_LARGE: &str = "file-too-large";
+
+/// 诊断中使用的文件名；非 file URI（`untitled:Untitled-1` 等）取最后一段路径，而不是整个 URI
+fn source_name(uri: &Url) -> String {
+    if let Ok(path) = uri.to_file_path() {
+        return path.to_string_lossy().to_string();
+    }
+
+    uri.path_segments()
+        .and_then(|segments| segments.filter(|it| !it.is_empty()).next_back())
+        .unwrap_or(uri.path())
+        .to_string()
+}
+
+/// 在内置作用域的副本上分析文档；未提供内置作用域时现场构建。
+/// 无论分析是否成功都返回类型表，补全等功能需要用到部分结果。
+/// `recover` 时遇到语法错误会修补后继续（见 [`recovery`]），返回每个修补过的语法错误
 fn analyze(
-    text: &str,
+    doc: &Document,
     uri: &Url,
+    builtins: Option<&TypeTable>,
+    recover: bool,
+) -> (Arc<Mutex<TypeTable>>, Vec<Diagnostic>) {
+    let table = match builtins {
+        Some(it) => it.clone(),
+        None => TypeTable::new().init(),
+    };
+    let table = Arc::new(Mutex::new(table));
+    let file = source_name(uri);
+
+    if !recover {
+        let result = run_passes(doc, file, Some(table.clone()));
+        return (table, result.err().into_iter().collect());
+    }
+
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    let mut patched = doc.clone();
+
+    for _ in 0..=recovery::MAX_RECOVERIES {
+        let Err(mut diag) = run_passes(&patched, file.clone(), Some(table.clone())) else {
+            break;
+        };
+        // 修补过的文本与原文偏移相同，但空白替换可能改变同一行上的 UTF-16 列号
+        let span = (
+            patched.lines.offset(diag.range.start),
+            patched.lines.offset(diag.range.end),
+        );
+        diag.range = doc.lines.range(span);
+
+        // 修补后的类型错误可能是跳过的语句造成的，只保留原文上的
+        let is_parse_error = diag.code == Some(NumberOrString::String(PARSE_ERROR.into()));
+        if diagnostics.is_empty() || is_parse_error {
+            diagnostics.push(diag);
+        }
+        if !is_parse_error {
+            break;
+        }
+        match recovery::patch(&patched.text, span.0) {
+            Some(text) => patched = Document::new(text),
+            None => break,
+        }
+    }
+
+    (table, diagnostics)
+}
+
+/// 依次运行上游各阶段，在第一个出错的阶段停止；没有类型表时只做词法和语法分析
+fn run_passes(
+    doc: &Document,
+    file: String,
 
     // 各种表
-    table: Arc<Mutex<TypeTable>>
+    table: Option<Arc<Mutex<TypeTable>>>,
 ) -> std::result::Result<(), Diagnostic> {
-    let file = uri
-        .to_file_path()
-        .map_or(uri.to_string(), |it| it.to_string_lossy().to_string());
-
     /* ---------- lexer ---------- */
-    let mut lexer = Lexer::new(text.to_string(), file.clone().into());
+    let mut lexer = Lexer::new(doc.text.clone(), file.clone().into());
     let tokens = lexer.get_tokens();
 
     if lexer.contains_error() {
         return Err(Diagnostic {
             severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(LEXER_ERROR.into())),
             message: "lexer error".into(),
             source: Some(file),
             ..Default::default()
@@ -108,39 +293,34 @@ fn analyze(
 
     /* ---------- parser ---------- */
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_program().map_err(|err| {
-        let line = (err.token.line - 1) as u32;
-        let (start, end) = calc_token_pos(text, &err.token);
-
-        Diagnostic {
-            range: Range {
-                start: Position { line, character: start },
-                end: Position { line, character: end },
-            },
-            severity: Some(DiagnosticSeverity::ERROR),
-            message: err.message.unwrap_or(err.kind.to_string().into()).to_string(),
-            source: Some(file.clone()),
-            ..Default::default()
-        }
+    let ast = parser.parse_program().map_err(|err| Diagnostic {
+        range: calc_token_range(doc, &err.token),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String(PARSE_ERROR.into())),
+        message: err
+            .message
+            .unwrap_or(err.kind.to_string().into())
+            .to_string(),
+        source: Some(file.clone()),
+        ..Default::default()
     })?;
 
     /* ---------- type checker ---------- */
-    let mut checker = TypeChecker::new(table.clone());
-
-    checker.check_node(ast).map_err(|err| {
-        let line = (err.token.line - 1) as u32;
-        let (start, end) = calc_token_pos(text, &err.token);
+    let Some(table) = table else {
+        return Ok(());
+    };
+    let mut checker = TypeChecker::new(table);
 
-        Diagnostic {
-            range: Range {
-                start: Position { line, character: start },
-                end: Position { line, character: end },
-            },
-            severity: Some(DiagnosticSeverity::ERROR),
-            message: err.message.unwrap_or(err.kind.to_string().into()).to_string(),
-            source: Some(file),
-            ..Default::default()
-        }
+    checker.check_node(ast).map_err(|err| Diagnostic {
+        range: calc_token_range(doc, &err.token),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String(TYPE_ERROR.into())),
+        message: err
+            .message
+            .unwrap_or(err.kind.to_string().into())
+            .to_string(),
+        source: Some(file),
+        ..Default::default()
     })?;
 
     Ok(())
@@ -150,45 +330,461 @@ fn analyze(
  * 文档事件专用：publish diagnostics
  * ========================= */
 
-async fn check_and_publish(
-    client: &Client,
+/// 完整的诊断流程：上游 lexer / parser / type checker，加上补充检查
+fn diagnose(
+    doc: &Document,
+    uri: &Url,
+    builtins: Option<&TypeTable>,
+    settings: &Settings,
+    recover: bool,
+) -> (Vec<Diagnostic>, Option<Arc<Mutex<TypeTable>>>) {
+    let (upstream, table) = diagnose_upstream(doc, uri, builtins, recover);
+    let fallback;
+    let builtins = match builtins {
+        Some(it) => it,
+        None => {
+            fallback = TypeTable::new().init();
+            &fallback
+        }
+    };
+    let diagnostics = with_checks(doc, uri, upstream, builtins, table.as_ref(), settings);
+    (diagnostics, table)
+}
+
+/// 上游 lexer / parser / type checker 的诊断，与配置无关，可以缓存。
+/// 只有分析成功时才返回类型表，语法错误恢复得到的表只用于补全
+fn diagnose_upstream(
+    doc: &Document,
     uri: &Url,
+    builtins: Option<&TypeTable>,
+    recover: bool,
+) -> (Vec<Diagnostic>, Option<Arc<Mutex<TypeTable>>>) {
+    match analyze(doc, uri, builtins, recover) {
+        (table, diagnostics) if diagnostics.is_empty() => (vec![], Some(table)),
+        (_, diagnostics) => (diagnostics, None),
+    }
+}
+
+/// 在上游诊断上加上补充检查，只用到缓存的 token 和大纲（以及给拼写建议用的内置作用域）；
+/// `table` 是上游分析成功时的类型表，有的话用于需要推断类型的检查
+fn with_checks(
+    doc: &Document,
+    uri: &Url,
+    mut diagnostics: Vec<Diagnostic>,
+    builtins: &TypeTable,
+    table: Option<&Arc<Mutex<TypeTable>>>,
+    settings: &Settings,
+) -> Vec<Diagnostic> {
+    // 有词法 / 语法错误时补充检查只会带来噪音
+    let syntax_ok = diagnostics.iter().all(|it| {
+        it.code != Some(NumberOrString::String(LEXER_ERROR.into()))
+            && it.code != Some(NumberOrString::String(PARSE_ERROR.into()))
+    });
+    if syntax_ok {
+        let source = source_name(uri);
+        let tokens = syntax::code_tokens(&doc.tokens);
+        let table = table.map(|it| it.lock().unwrap());
+
+        let ctx = CheckContext {
+            doc,
+            uri,
+            source: &source,
+            tokens: &tokens,
+            outline: &doc.outline,
+            settings: &settings.diagnostics,
+            table: table.as_deref(),
+        };
+        checks::merge(&mut diagnostics, checks::run(&ctx));
+        checks::suggest_names(&ctx, builtins, &mut diagnostics);
+    }
+
+    diagnostics
+}
+
+/// 从磁盘读取并分析文件，不要求文件在编辑器中打开；读取失败时返回一条诊断而不是报错。
+/// 不做语法错误恢复，只报告第一个语法错误
+pub fn check_file(path: &Path) -> Vec<Diagnostic> {
+    let io_error = |message: String| Diagnostic {
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String(IO_ERROR.into())),
+        message,
+        source: Some(path.display().to_string()),
+        ..Default::default()
+    };
+
+    let uri = match std::path::absolute(path).map(Url::from_file_path) {
+        Ok(Ok(uri)) => uri,
+        _ => return vec![io_error(format!("invalid path: {}", path.display()))],
+    };
+
+    match std::fs::read_to_string(path) {
+        Ok(text) => {
+            diagnose(
+                &Document::new(text),
+                &uri,
+                None,
+                &Settings::default(),
+                false,
+            )
+            .0
+        }
+        Err(err) => vec![io_error(format!("cannot read {}: {err}", path.display()))],
+    }
+}
+
+/// 目录下的所有源文件（递归，跳过隐藏目录和 `target`），按路径排序
+pub fn source_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    workspace::collect_sources(dir, &mut files);
+    files.sort();
+    files
+}
+
+/// 格式化一段源码，不要求对应任何文档；无法解析时返回第一个词法或语法错误
+pub fn format_text(
     text: &str,
-) -> Option<Arc<Mutex<TypeTable>>> {
-    let table = Arc::new(Mutex::new(TypeTable::new().init()));
-    match analyze(text, uri, table.clone()) {
-        Ok(_) => {
-            client.publish_diagnostics(uri.clone(), vec![], None).await;
-            Some(table)
-        }
-        Err(diag) => {
-            client
-                .publish_diagnostics(uri.clone(), vec![diag], None)
+    options: &FormattingOptions,
+    settings: &FormatSettings,
+) -> std::result::Result<String, Diagnostic> {
+    let doc = Document::new(text.to_string());
+    run_passes(&doc, "<input>".into(), None)?;
+
+    Ok(format::format(&doc, options, settings))
+}
+
+/// 源码中 `pos` 处可见的所有符号，内层作用域在前；只依赖声明大纲，代码不完整时也可用
+pub fn visible_symbols_at(text: &str, pos: Position) -> Vec<SymbolInfo> {
+    let doc = Document::new(text.to_string());
+    let offset = doc.lines.offset(pos);
+
+    symbols::visible_symbols(&doc, offset, &TypeTable::new().init())
+}
+
+impl Backend {
+    async fn check_and_publish(&self, uri: &Url, doc: &Document) -> Option<Arc<Mutex<TypeTable>>> {
+        let excluded = !self.workspace.read().await.includes(uri);
+        let (upstream, diagnostics, table, elapsed) = if *self.diagnostics_paused.read().await
+            || excluded
+        {
+            self.upstream.write().await.remove(uri);
+            (None, vec![], None, None)
+        } else if self.too_large(doc).await {
+            self.upstream.write().await.remove(uri);
+            let diag = Diagnostic {
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String(FILE_TOO_LARGE.into())),
+                message: "file too large; analysis disabled".into(),
+                source: Some(source_name(uri)),
+                ..Default::default()
+            };
+            (None, vec![diag], None, None)
+        } else {
+            let started = Instant::now();
+            let (upstream, table) = diagnose_upstream(doc, uri, Some(self.builtins()), true);
+            let settings = self.settings.read().await.clone();
+            let (builtins, types) = (self.builtins(), table.as_ref());
+            let diagnostics = with_checks(doc, uri, upstream.clone(), builtins, types, &settings);
+
+            let elapsed = started.elapsed();
+            let message = format!("analyzed {uri} in {elapsed:?}");
+            self.log_trace(message, || {
+                let codes: Vec<String> = diagnostics
+                    .iter()
+                    .map(|it| match &it.code {
+                        Some(NumberOrString::String(code)) => code.clone(),
+                        Some(NumberOrString::Number(code)) => code.to_string(),
+                        None => "-".into(),
+                    })
+                    .collect();
+                format!("{} diagnostics: [{}]", diagnostics.len(), codes.join(", "))
+            })
+            .await;
+            (Some(upstream), diagnostics, table, Some(elapsed))
+        };
+
+        let current = self
+            .store_and_publish(uri, doc, upstream, diagnostics, elapsed)
+            .await;
+        if !current {
+            let message = format!("discarded stale analysis of {uri}");
+            self.log_trace(message, String::new).await;
+        }
+        if let Some(table) = table.as_ref().filter(|_| current) {
+            let table = table.lock().unwrap().clone();
+            self.update_type_table(uri, &doc.text, table).await;
+        }
+        table
+    }
+
+    /// 打开文档时的检查：按配置沿用内容相同时的上游诊断
+    async fn check_opened(&self, uri: &Url, doc: &Document) {
+        if self.settings.read().await.reuse_analysis_on_open {
+            let cached = self
+                .analyses
+                .read()
+                .await
+                .get(uri, &doc.text)
+                .map(<[_]>::to_vec);
+            if let Some(upstream) = cached {
+                self.log_trace(format!("reused analysis of {uri}"), String::new)
+                    .await;
+                self.upstream.write().await.insert(uri.clone(), upstream);
+                self.recheck(uri, doc).await;
+                return;
+            }
+        }
+        self.check_and_publish(uri, doc).await;
+    }
+
+    /// 配置变化后重新检查：有缓存的上游诊断时只重跑补充检查
+    async fn recheck(&self, uri: &Url, doc: &Document) {
+        let cached = self.upstream.read().await.get(uri).cloned();
+        let skip = self.skips_analysis(uri, doc).await;
+
+        match cached {
+            Some(upstream) if !skip => {
+                let settings = self.settings.read().await.clone();
+                let table = self.current_type_table(uri, doc).await;
+                let diagnostics = with_checks(
+                    doc,
+                    uri,
+                    upstream,
+                    self.builtins(),
+                    table.as_ref(),
+                    &settings,
+                );
+                self.store_and_publish(uri, doc, None, diagnostics, None)
+                    .await;
+            }
+            _ => {
+                self.check_and_publish(uri, doc).await;
+            }
+        }
+    }
+
+    /// 文档仍以分析时的内容打开时才保存并发布结果，返回是否发布。
+    /// 分析期间文档被关闭或修改时丢弃结果，以免给已关闭的文件留下诊断，或用旧结果覆盖新结果
+    async fn store_and_publish(
+        &self,
+        uri: &Url,
+        doc: &Document,
+        upstream: Option<Vec<Diagnostic>>,
+        diagnostics: Vec<Diagnostic>,
+        elapsed: Option<Duration>,
+    ) -> bool {
+        // 发布完成前一直持有读锁，did_close 清空诊断总在这之后
+        let docs = self.documents.read().await;
+        if docs.get(uri).is_none_or(|it| it.text != doc.text) {
+            return false;
+        }
+
+        if let Some(upstream) = upstream {
+            self.analyses
+                .write()
+                .await
+                .insert(uri.clone(), &doc.text, upstream.clone());
+            self.upstream.write().await.insert(uri.clone(), upstream);
+        }
+        self.diagnostics
+            .write()
+            .await
+            .insert(uri.clone(), diagnostics.clone());
+        self.publish(uri, &diagnostics, elapsed).await;
+        true
+    }
+
+    /// 诊断暂停、被排除或过大的文件不分析，也不沿用之前的结果，交给 `check_and_publish` 处理
+    async fn skips_analysis(&self, uri: &Url, doc: &Document) -> bool {
+        *self.diagnostics_paused.read().await
+            || self.too_large(doc).await
+            || !self.workspace.read().await.includes(uri)
+    }
+
+    /// 超过 `maxFileSizeBytes` 的文件跳过分析，保证服务器响应
+    async fn too_large(&self, doc: &Document) -> bool {
+        doc.text.len() > self.settings.read().await.max_file_size_bytes
+    }
+
+    /// 按当前配置过滤后发布；`elapsed` 是分析耗时，只写入调试日志。
+    /// 与上次发布的内容相同时跳过，连续保存时编辑器中的波浪线不会闪烁
+    async fn publish(&self, uri: &Url, diagnostics: &[Diagnostic], elapsed: Option<Duration>) {
+        let filtered = self.settings.read().await.diagnostics.filter(diagnostics);
+
+        let hash = diagnostics_hash(&filtered);
+        if self.published.write().await.insert(uri.clone(), hash) == Some(hash) {
+            return;
+        }
+        if let Some(log) = &self.diagnostics_log {
+            let version = self.versions.read().await.get(uri).copied();
+            log.record(uri, version, elapsed, &filtered);
+        }
+        self.client
+            .publish_diagnostics(uri.clone(), filtered, None)
+            .await;
+    }
+
+    /// 按配置来源重新计算配置；只影响过滤的变化不必重新检查
+    async fn apply_settings(&self) {
+        let (settings, errors) = self.config_sources.read().await.settings();
+        for error in errors {
+            let message = format!("ignored invalid setting {error}");
+            self.client.log_message(MessageType::WARNING, message).await;
+        }
+        let reanalyze = self.settings.read().await.affects_analysis(&settings);
+        let files_changed = self.settings.read().await.files != settings.files
+            || self.settings.read().await.workspace.indexing != settings.workspace.indexing;
+        let closed_files = settings.workspace.diagnostics.closed_files;
+
+        if files_changed {
+            self.reindex(settings.files.clone(), settings.workspace.indexing)
                 .await;
-            None
+        }
+        *self.settings.write().await = settings;
+        self.closed.write().await.forget_diagnostics();
+
+        if reanalyze {
+            let docs = self.documents.read().await.clone();
+            for (uri, doc) in &docs {
+                self.recheck(uri, doc).await;
+            }
+        } else {
+            self.republish_all().await;
+        }
+
+        // 关掉 `closedFiles` 后清除已关闭文件的诊断
+        if !closed_files {
+            let closed = std::mem::take(&mut *self.closed_diagnostics.write().await);
+            for uri in closed.keys() {
+                self.publish(uri, &[], None).await;
+            }
+        } else if reanalyze {
+            let closed: Vec<_> = self
+                .closed_diagnostics
+                .read()
+                .await
+                .keys()
+                .cloned()
+                .collect();
+            for uri in &closed {
+                self.check_closed(uri).await;
+            }
+        }
+    }
+
+    /// 要分析的文件或索引方式变化后重建工作区索引，打开的文档以编辑器内容为准。
+    /// 初次索引之前只记下配置，由 `initialized` 建立索引
+    async fn reindex(&self, files: FileSettings, indexing: Indexing) {
+        let docs = self.documents.read().await.clone();
+        {
+            let mut workspace = self.workspace.write().await;
+            workspace.set_filter(files);
+            workspace.set_lazy(indexing == Indexing::Lazy);
+            if !workspace.ready() {
+                return;
+            }
+            workspace.clear();
+        }
+
+        self.index_workspace().await;
+
+        let mut workspace = self.workspace.write().await;
+        for (uri, doc) in &docs {
+            workspace.index_file(uri.clone(), &doc.text);
+        }
+    }
+
+    /// 按 `workspace.indexing` 建立工作区索引：`eager` 时扫描所有根目录并报告进度，
+    /// `lazy` 时什么都不扫描，文件在打开或被 import 时才索引
+    async fn index_workspace(&self) {
+        let (roots, lazy) = {
+            let workspace = self.workspace.read().await;
+            (
+                workspace.roots().cloned().collect::<Vec<_>>(),
+                workspace.is_lazy(),
+            )
+        };
+
+        if !lazy {
+            let progress = self.begin_progress("Indexing workspace").await;
+            for (i, root) in roots.iter().enumerate() {
+                let percentage = (i * 100 / roots.len()) as u32;
+                self.report_progress(progress.as_ref(), root.display().to_string(), percentage)
+                    .await;
+                self.scan_root(root).await;
+            }
+            self.end_progress(progress).await;
+        }
+
+        self.workspace.write().await.mark_ready();
+    }
+
+    /// 在后台读入目录下的源文件，只在加入索引时持有锁
+    async fn scan_root(&self, root: &Path) {
+        let task_root = root.to_path_buf();
+        let files = tokio::task::spawn_blocking(move || workspace::read_sources(&task_root))
+            .await
+            .unwrap_or_default();
+        self.workspace.write().await.index_sources(files);
+    }
+
+    /// 配置变化后用缓存的诊断重新发布，不重新分析
+    async fn republish_all(&self) {
+        let cached = self.diagnostics.read().await.clone();
+        let closed = self.closed_diagnostics.read().await.clone();
+        for (uri, diagnostics) in cached.iter().chain(&closed) {
+            self.publish(uri, diagnostics, None).await;
         }
     }
 }
 
+fn diagnostics_hash(diagnostics: &[Diagnostic]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 工作区根目录：优先 workspace folders，否则退回 root_uri
+#[allow(deprecated)]
+fn workspace_roots(params: &InitializeParams) -> Vec<std::path::PathBuf> {
+    let uris: Vec<Url> = match &params.workspace_folders {
+        Some(folders) => folders.iter().map(|it| it.uri.clone()).collect(),
+        None => params.root_uri.iter().cloned().collect(),
+    };
+
+    uris.iter()
+        .filter_map(|it| it.to_file_path().ok())
+        .collect()
+}
+
 /* =========================
  * LSP impl
  * ========================= */
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        self.workspace
+            .write()
+            .await
+            .set_roots(workspace_roots(&params));
+        *self.client_capabilities.write().await = params.capabilities.clone();
+        if let Some(trace) = params.trace {
+            *self.trace.write().await = trace;
+        }
+
+        let encoding = capabilities::position_encoding(&params.capabilities);
+        self.workspace.write().await.set_encoding(encoding.clone());
+        let _ = self.position_encoding.set(encoding);
+
+        let capabilities = capabilities::negotiate(&params.capabilities);
+        *self.server_capabilities.write().await = capabilities.clone();
+
         Ok(InitializeResult {
-            capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
-                )),
-                completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec!["_".into()]),
-                    resolve_provider: Some(false),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            },
+            capabilities,
             server_info: Some(ServerInfo {
                 name: "TypedAnt LSP".into(),
                 version: Some("0.1.0".into()),
@@ -196,61 +792,520 @@ impl LanguageServer for Backend {
         })
     }
 
+    async fn initialized(&self, _: InitializedParams) {
+        // 先读配置（客户端的和配置文件的），索引方式和要索引的文件都由配置决定
+        self.pull_client_config().await;
+        self.reload_config_file().await;
+        self.index_workspace().await;
+        self.register_file_watchers().await;
+        self.register_configuration().await;
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         let uri = params.text_document.uri;
         let text = params.text_document.text;
+        self.versions
+            .write()
+            .await
+            .insert(uri.clone(), params.text_document.version);
+
+        self.closed_diagnostics.write().await.remove(&uri);
+        let cached = self.closed.write().await.take(&uri, &text);
+        let reusable = match &cached {
+            Some((doc, ..)) => !self.skips_analysis(&uri, doc).await,
+            None => false,
+        };
 
-        self.documents.write().await.insert(uri.clone(), text.clone());
-        check_and_publish(&self.client, &uri, &text).await;
+        match cached {
+            // 内容没变，直接用关闭前的结果
+            Some((doc, Some(diagnostics), table)) if reusable => {
+                let upstream = self
+                    .analyses
+                    .read()
+                    .await
+                    .get(&uri, &text)
+                    .map(<[_]>::to_vec);
+                if let Some(upstream) = upstream {
+                    self.upstream.write().await.insert(uri.clone(), upstream);
+                }
+                if let Some(table) = table {
+                    self.update_type_table(&uri, &text, table).await;
+                }
+                self.documents.write().await.insert(uri.clone(), doc);
+                self.diagnostics
+                    .write()
+                    .await
+                    .insert(uri.clone(), diagnostics.clone());
+                self.publish(&uri, &diagnostics, None).await;
+            }
+            Some((doc, ..)) => {
+                self.documents
+                    .write()
+                    .await
+                    .insert(uri.clone(), doc.clone());
+                self.check_opened(&uri, &doc).await;
+            }
+            None => {
+                let doc = self.new_document(text);
+                self.documents
+                    .write()
+                    .await
+                    .insert(uri.clone(), doc.clone());
+                self.workspace
+                    .write()
+                    .await
+                    .index_file(uri.clone(), &doc.text);
+                self.check_opened(&uri, &doc).await;
+            }
+        }
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         let uri = params.text_document.uri;
+        if params.content_changes.is_empty() {
+            return;
+        }
+        self.versions
+            .write()
+            .await
+            .insert(uri.clone(), params.text_document.version);
 
-        if let Some(change) = params.content_changes.last() {
-            let text = change.text.clone();
-            self.documents.write().await.insert(uri.clone(), text.clone());
-            check_and_publish(&self.client, &uri, &text).await;
+        let mut text = self
+            .documents
+            .read()
+            .await
+            .get(&uri)
+            .map(|it| it.text.clone())
+            .unwrap_or_default();
+
+        // FULL 同步下每个修改都应是完整文本；带 range 的修改按增量处理，而不是把片段当成整个文档
+        let mut warned = false;
+        let encoding = self.position_encoding();
+        for change in params.content_changes {
+            if change.range.is_some() && !warned {
+                warned = true;
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("received an incremental change under full sync: {uri}"),
+                    )
+                    .await;
+            }
+            text = document::apply_change(&text, &change, &encoding);
         }
+
+        let doc = self.new_document(text);
+        self.documents
+            .write()
+            .await
+            .insert(uri.clone(), doc.clone());
+        self.workspace
+            .write()
+            .await
+            .index_file(uri.clone(), &doc.text);
+        self.check_and_publish(&uri, &doc).await;
+        self.reanalyze_dependents(&uri).await;
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        self.reanalyze_dependents(&params.text_document.uri).await;
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        self.documents.write().await.remove(&params.text_document.uri);
-        self.client
-            .publish_diagnostics(params.text_document.uri, vec![], None)
-            .await;
+        let uri = &params.text_document.uri;
+        let doc = self.documents.write().await.remove(uri);
+        let diagnostics = self.diagnostics.write().await.remove(uri);
+        self.upstream.write().await.remove(uri);
+        let table = self.type_tables.write().await.remove(uri);
+        self.completion_analyses.lock().unwrap().remove(uri);
+        self.versions.write().await.remove(uri);
+
+        if let Some(doc) = doc {
+            // 类型表可能来自更早的内容，只保留与关闭时内容一致的
+            let hash = content_hash(&doc.text);
+            let table = table.filter(|(.., it)| *it == hash).map(|(it, ..)| it);
+            let capacity = self.settings.read().await.closed_document_cache;
+            self.closed
+                .write()
+                .await
+                .insert(uri.clone(), doc, diagnostics, table, capacity);
+        }
+
+        if self
+            .settings
+            .read()
+            .await
+            .workspace
+            .diagnostics
+            .closed_files
+        {
+            self.check_closed(uri).await;
+        } else {
+            self.publish(uri, &[], None).await;
+        }
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // 拉取模式的客户端只通知有变化，不带内容
+        if params.settings.is_null() {
+            if !self.pull_client_config().await {
+                return;
+            }
+        } else {
+            self.config_sources.write().await.client = params.settings;
+        }
+        self.apply_settings().await;
+    }
+
+    /// 加入或移除工作区目录后，打开的文档按新的目录归属重新索引和分析
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let path = |folder: &WorkspaceFolder| folder.uri.to_file_path().ok();
+        let mut rescan = vec![];
+        {
+            let mut workspace = self.workspace.write().await;
+            for root in params.event.removed.iter().filter_map(path) {
+                if workspace.remove_root(&root) {
+                    rescan.push(root);
+                }
+            }
+            for root in params.event.added.iter().filter_map(path) {
+                if workspace.add_root(root.clone()) {
+                    rescan.push(root);
+                }
+            }
+        }
+        for root in &rescan {
+            self.scan_root(root).await;
+        }
+
+        // 打开的文件以编辑器内容为准
+        let docs = self.documents.read().await.clone();
+        for (uri, doc) in &docs {
+            self.workspace
+                .write()
+                .await
+                .index_file(uri.clone(), &doc.text);
+        }
+
+        self.reload_config_file().await;
+        for (uri, doc) in &docs {
+            self.check_and_publish(uri, doc).await;
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        self.on_watched_files(params.changes).await;
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         let uri = params.text_document_position.text_document.uri;
         let pos = params.text_document_position.position;
 
+        // 补全可能要等分析，不能一直占着文档的锁
+        let doc = match self.documents.read().await.get(&uri) {
+            Some(it) => it.clone(),
+            None => return Ok(None),
+        };
+
+        let offset = doc.lines.offset(pos);
+        let trigger = params
+            .context
+            .as_ref()
+            .and_then(|it| it.trigger_character.as_deref());
+        if self.too_large(&doc).await
+            || completion::in_string_or_comment(&doc, offset)
+            || !completion::wants_completion(&doc, offset, trigger)
+        {
+            return Ok(None);
+        }
+
+        // 手动请求的补全总是给出，自动触发的要等名字足够长
+        let invoked = params
+            .context
+            .as_ref()
+            .is_none_or(|it| it.trigger_kind == CompletionTriggerKind::INVOKED);
+        let min_prefix = self.settings.read().await.completion.min_prefix_length;
+        if !invoked && completion::current_ident(&doc, pos).chars().count() < min_prefix {
+            return Ok(None);
+        }
+
+        let items = self.completion_items(&uri, &doc, pos).await;
+
+        let message = format!("completion at {uri}:{}:{}", pos.line + 1, pos.character + 1);
+        self.log_trace(message, || {
+            let labels: Vec<&str> = items.iter().map(|it| it.label.as_str()).collect();
+            format!("{} items: [{}]", items.len(), labels.join(", "))
+        })
+        .await;
+
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
+        Ok(self.resolve_completion(params).await)
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+
         let docs = self.documents.read().await;
-        let text = match docs.get(&uri) {
+        let doc = match docs.get(&uri) {
             Some(it) => it,
             None => return Ok(None),
         };
 
-        let table = Arc::new(Mutex::new(TypeTable::new().init()));
-        let _err = analyze(text, &uri, table.clone());
-
-        let prefix = current_ident(text, pos);
-
-        let items = table
-            .lock()
-            .unwrap()
-            .var_map
-            .keys()
-            .filter(|name| name.starts_with(&prefix))
-            .map(|name| CompletionItem {
-                label: name.to_string(),
-                kind: Some(CompletionItemKind::VARIABLE),
-                insert_text: Some(name.to_string()),
-                ..Default::default()
-            })
+        let markup = capabilities::hover_markup(&*self.client_capabilities.read().await);
+        Ok(hover::hover(doc, pos, self.builtins(), markup))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        Ok(Some(self.workspace_symbols(params).await))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&params.text_document.uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(Some(DocumentSymbolResponse::Nested(doc.document_symbols())))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&params.text_document.uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic::semantic_tokens(doc, None),
+        })))
+    }
+
+    /// 只计算可见范围，大文件滚动时不必每次处理整个文档
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&params.text_document.uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        let span = (
+            doc.lines.offset(params.range.start),
+            doc.lines.offset(params.range.end),
+        );
+        Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic::semantic_tokens(doc, Some(span)),
+        })))
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let uri = params.text_document.uri;
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(Some(links::document_links(&uri, doc)))
+    }
+
+    async fn document_link_resolve(&self, params: DocumentLink) -> Result<DocumentLink> {
+        Ok(links::resolve_link(params))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        let actions = self
+            .quick_fixes(&uri, doc, &params.context.diagnostics)
+            .await;
+
+        Ok((!actions.is_empty()).then_some(actions))
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        let (tokens, outline) = (&doc.tokens, &doc.outline);
+
+        let Some(decl) =
+            references::definition_at(&doc.text, tokens, outline, doc.lines.offset(pos))
+        else {
+            return Ok(None);
+        };
+
+        let ranges = references::references(&doc.text, tokens, outline, decl)
+            .into_iter()
+            .map(|span| doc.lines.range(span))
             .collect();
 
-        Ok(Some(CompletionResponse::Array(items)))
+        Ok(Some(LinkedEditingRanges {
+            ranges,
+            word_pattern: None,
+        }))
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(call_hierarchy::prepare(&uri, doc, pos))
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let uri = params.item.uri.clone();
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(call_hierarchy::incoming(&uri, doc, &params.item))
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let uri = params.item.uri.clone();
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(call_hierarchy::outgoing(&uri, doc, &params.item))
+    }
+
+    /// 类型来自最近一次成功的分析，文档当前有错误时提示可能是旧的
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        let tables = self.type_tables.read().await;
+        let Some((table, ..)) = tables.get(&uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(inlay::inlay_hints(doc, table, params.range)))
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&params.text_document.uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        Ok(Some(folding::folding_ranges(doc)))
+    }
+
+    async fn inline_value(&self, params: InlineValueParams) -> Result<Option<Vec<InlineValue>>> {
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&params.text_document.uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        let stopped = params.context.stopped_location;
+        Ok(Some(inline_value::inline_values(
+            doc,
+            params.range,
+            stopped,
+        )))
+    }
+
+    async fn moniker(&self, params: MonikerParams) -> Result<Option<Vec<Moniker>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+
+        let docs = self.documents.read().await;
+        let doc = match docs.get(&uri) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+
+        let workspace = self.workspace.read().await;
+        Ok(moniker::monikers(
+            &uri,
+            doc,
+            pos,
+            &workspace,
+            self.builtins(),
+        ))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        match params.command.as_str() {
+            custom::TOGGLE_DIAGNOSTICS => {
+                self.toggle_diagnostics().await;
+                Ok(None)
+            }
+            custom::NEXT_DIAGNOSTIC => self.goto_diagnostic(params.arguments, true).await,
+            custom::PREV_DIAGNOSTIC => self.goto_diagnostic(params.arguments, false).await,
+            custom::FIX_ALL => self.fix_all(params.arguments).await,
+            _ => Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "unknown command: {}",
+                params.command
+            ))),
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
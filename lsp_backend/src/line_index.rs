@@ -0,0 +1,214 @@
+//! 行索引：字节偏移与 LSP 位置之间的对数时间转换
+//!
+//! 记录每行起始偏移，以及每行中的非 ASCII 字符；
+//! 纯 ASCII 的部分字节、字符、UTF-16 三种列号相同，只需在非 ASCII 字符处修正。
+//! LSP 位置的列号单位由客户端协商（`positionEncoding`），默认 UTF-16。
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{Position, PositionEncodingKind, Range};
+
+use crate::utils::UTF16Len;
+
+/// 行内一个非 ASCII 字符，三种单位下的起始列
+#[derive(Debug, Clone, Copy)]
+struct WideChar {
+    byte: usize,
+    utf16: usize,
+    char: usize,
+    len_utf8: usize,
+    len_utf16: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Byte,
+    Utf16,
+    Char,
+}
+
+impl WideChar {
+    fn start(&self, unit: Unit) -> usize {
+        match unit {
+            Unit::Byte => self.byte,
+            Unit::Utf16 => self.utf16,
+            Unit::Char => self.char,
+        }
+    }
+
+    fn end(&self, unit: Unit) -> usize {
+        self.start(unit)
+            + match unit {
+                Unit::Byte => self.len_utf8,
+                Unit::Utf16 => self.len_utf16,
+                Unit::Char => 1,
+            }
+    }
+}
+
+impl Unit {
+    fn of(encoding: &PositionEncodingKind) -> Self {
+        match encoding.as_str() {
+            "utf-8" => Unit::Byte,
+            "utf-32" => Unit::Char,
+            _ => Unit::Utf16,
+        }
+    }
+
+    fn width(self, text: &str) -> usize {
+        match self {
+            Unit::Byte => text.len(),
+            Unit::Utf16 => text.utf16_len(),
+            Unit::Char => text.chars().count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+    wide_chars: HashMap<usize, Vec<WideChar>>,
+    len: usize,
+    /// LSP 位置中列号的单位
+    unit: Unit,
+}
+
+impl LineIndex {
+    /// 列号按 UTF-16 计的行索引
+    pub fn new(text: &str) -> Self {
+        Self::with_encoding(text, &PositionEncodingKind::UTF16)
+    }
+
+    /// 列号按协商出的编码计的行索引，不认识的编码按 UTF-16 处理
+    pub fn with_encoding(text: &str, encoding: &PositionEncodingKind) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars: HashMap<usize, Vec<WideChar>> = HashMap::new();
+
+        let mut line = 0;
+        let mut utf16 = 0;
+        let mut chars = 0;
+
+        for (i, c) in text.char_indices() {
+            if c == '\n' {
+                line_starts.push(i + 1);
+                line += 1;
+                utf16 = 0;
+                chars = 0;
+                continue;
+            }
+
+            if !c.is_ascii() {
+                wide_chars.entry(line).or_default().push(WideChar {
+                    byte: i - line_starts[line],
+                    utf16,
+                    char: chars,
+                    len_utf8: c.len_utf8(),
+                    len_utf16: c.len_utf16(),
+                });
+            }
+            utf16 += c.len_utf16();
+            chars += 1;
+        }
+
+        Self {
+            line_starts,
+            wide_chars,
+            len: text.len(),
+            unit: Unit::of(encoding),
+        }
+    }
+
+    /// 协商出的位置编码
+    pub fn encoding(&self) -> PositionEncodingKind {
+        match self.unit {
+            Unit::Byte => PositionEncodingKind::UTF8,
+            Unit::Utf16 => PositionEncodingKind::UTF16,
+            Unit::Char => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// 文本在 LSP 位置中占的列数
+    pub fn width(&self, text: &str) -> usize {
+        self.unit.width(text)
+    }
+
+    /// 行的字节范围（不含换行符）；行号越界时返回 None
+    pub fn line_span(&self, line: usize) -> Option<(usize, usize)> {
+        let start = *self.line_starts.get(line)?;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.len, |next| next - 1);
+
+        Some((start, end))
+    }
+
+    /// 行内列号在两种单位之间转换，落在多单元字符中间时取其起点
+    fn convert(&self, line: usize, col: usize, from: Unit, to: Unit) -> usize {
+        let Some(wide) = self.wide_chars.get(&line) else {
+            return col;
+        };
+
+        let idx = wide.partition_point(|w| w.start(from) < col);
+        let Some(w) = idx.checked_sub(1).map(|i| wide[i]) else {
+            return col;
+        };
+
+        if col < w.end(from) {
+            w.start(to)
+        } else {
+            col - w.end(from) + w.end(to)
+        }
+    }
+
+    /// 字节偏移 → LSP 位置
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|s| *s <= offset) - 1;
+        let col = self.convert(line, offset - self.line_starts[line], Unit::Byte, self.unit);
+
+        Position::new(line as u32, col as u32)
+    }
+
+    pub fn range(&self, span: (usize, usize)) -> Range {
+        Range::new(self.position(span.0), self.position(span.1))
+    }
+
+    /// LSP 位置 → 字节偏移，越界时落在行尾 / 文本末尾
+    pub fn offset(&self, position: Position) -> usize {
+        let line = position.line as usize;
+        let Some((start, end)) = self.line_span(line) else {
+            return self.len;
+        };
+
+        let col = self.convert(line, position.character as usize, self.unit, Unit::Byte);
+        (start + col).min(end)
+    }
+
+    /// 上游 token 的行列号（从 1 开始，列按字符计）→ 字节偏移
+    pub fn char_offset(&self, line: usize, column: usize) -> usize {
+        let line = line.saturating_sub(1);
+        let Some((start, end)) = self.line_span(line) else {
+            return self.len;
+        };
+
+        let col = self.convert(line, column.saturating_sub(1), Unit::Char, Unit::Byte);
+        (start + col).min(end)
+    }
+
+    /// 上游 token 的范围，`line` / `column` 同 [`LineIndex::char_offset`]。
+    /// 多行 token（跨行的字符串、块注释）的终点按其中的换行计算，而不是在首行上累加长度
+    pub fn token_range(&self, line: usize, column: usize, value: &str) -> Range {
+        let start = self.position(self.char_offset(line, column));
+
+        let end = match value.rsplit_once('\n') {
+            Some((head, last)) => Position::new(
+                start.line + head.matches('\n').count() as u32 + 1,
+                self.width(last) as u32,
+            ),
+            None => Position::new(start.line, start.character + self.width(value) as u32),
+        };
+
+        Range::new(start, end)
+    }
+}
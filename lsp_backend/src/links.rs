@@ -0,0 +1,95 @@
+//! 字符串字面量中的文件路径和 URL（`textDocument/documentLink`）
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::syntax::TokenKind;
+use crate::workspace::resolve_import;
+
+/// 超过这个大小的文件先不计算链接目标，等客户端 resolve 时再算
+const LAZY_LINKS_BYTES: usize = 256 * 1024;
+
+/// 延迟解析时放在 `DocumentLink::data` 中的信息
+#[derive(Debug, Serialize, Deserialize)]
+struct LinkData {
+    base: Url,
+    path: String,
+}
+
+fn is_url(text: &str) -> bool {
+    text.starts_with("http://") || text.starts_with("https://")
+}
+
+fn resolve_target(base: &Url, path: &str) -> Option<Url> {
+    if is_url(path) {
+        return Url::parse(path).ok();
+    }
+
+    let from = base.to_file_path().ok()?;
+    Url::from_file_path(resolve_import(&from, path)).ok()
+}
+
+pub(crate) fn document_links(uri: &Url, doc: &Document) -> Vec<DocumentLink> {
+    let text = doc.text.as_str();
+    let lazy = text.len() > LAZY_LINKS_BYTES;
+
+    let mut links = vec![];
+    for (i, tok) in doc.tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Str || tok.end - tok.start < 2 {
+            continue;
+        }
+        let start = tok.start + 1;
+        let end = if text[..tok.end].ends_with('"') {
+            tok.end - 1
+        } else {
+            tok.end
+        };
+        let path = &text[start..end];
+
+        let is_import = i
+            .checked_sub(1)
+            .is_some_and(|p| doc.tokens[p].is(text, "import"));
+        let is_path = path.starts_with("./") || path.starts_with("../");
+        if path.is_empty() || !(is_import || is_path || is_url(path)) {
+            continue;
+        }
+
+        let (target, data) = if lazy {
+            let data = LinkData {
+                base: uri.clone(),
+                path: path.to_string(),
+            };
+            (None, serde_json::to_value(data).ok())
+        } else {
+            match resolve_target(uri, path) {
+                Some(target) => (Some(target), None),
+                None => continue,
+            }
+        };
+
+        links.push(DocumentLink {
+            range: doc.lines.range((start, end)),
+            target,
+            tooltip: None,
+            data,
+        });
+    }
+
+    links
+}
+
+/// `documentLink/resolve`：补上延迟计算的目标
+pub(crate) fn resolve_link(mut link: DocumentLink) -> DocumentLink {
+    if link.target.is_none() {
+        let data = link
+            .data
+            .take()
+            .and_then(|it| serde_json::from_value::<LinkData>(it).ok());
+
+        if let Some(data) = data {
+            link.target = resolve_target(&data.base, &data.path);
+        }
+    }
+    link
+}
@@ -0,0 +1,163 @@
+//! 跨索引的符号标识（`textDocument/moniker`）
+//!
+//! 标识由模块路径和符号名组成，只要不改名、不移动文件就保持不变。
+
+use std::path::Path;
+
+use ant_type_checker::table::TypeTable;
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::outline::{Outline, SymbolKind};
+use crate::references;
+use crate::workspace::{SOURCE_EXT, WorkspaceIndex};
+
+pub(crate) const SCHEME: &str = "typedant";
+
+/// 文件的模块路径：相对所在工作区根目录、去掉扩展名、用 `/` 分隔；
+/// 不在任何根目录下（或不是文件）时只取文件名
+fn module_path(uri: &Url, root: Option<&Path>) -> String {
+    let Ok(path) = uri.to_file_path() else {
+        let name = uri
+            .path_segments()
+            .and_then(|segments| segments.filter(|it| !it.is_empty()).next_back())
+            .unwrap_or(uri.path());
+        return name.trim_end_matches(&format!(".{SOURCE_EXT}")).to_string();
+    };
+
+    let path = path.with_extension("");
+    let relative = root.and_then(|root| path.strip_prefix(root).ok());
+
+    let Some(relative) = relative else {
+        return path
+            .file_name()
+            .map_or(String::new(), |it| it.to_string_lossy().to_string());
+    };
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 声明在文件内的路径：外层函数名、所属结构体或函数名，最后是自身的名字
+fn symbol_path(outline: &Outline, decl: usize) -> Vec<&str> {
+    let sym = &outline.symbols[decl];
+    let mut path = vec![sym.name.as_str()];
+
+    let mut owner = sym.parent.or_else(|| enclosing_function(outline, decl));
+    while let Some(idx) = owner {
+        path.push(outline.symbols[idx].name.as_str());
+        owner = outline.symbols[idx]
+            .parent
+            .or_else(|| enclosing_function(outline, idx));
+    }
+
+    path.reverse();
+    path
+}
+
+/// 函数体包含 `decl` 的最内层函数，不是 `decl` 自身
+fn enclosing_function(outline: &Outline, decl: usize) -> Option<usize> {
+    let sym = &outline.symbols[decl];
+    // 函数的范围包括它自己的名字，从声明之前开始找
+    let offset = match sym.kind {
+        SymbolKind::Function => sym.span.0.checked_sub(1)?,
+        _ => sym.name_span.0,
+    };
+
+    outline.enclosing_function(offset).filter(|it| *it != decl)
+}
+
+/// 同一路径下有多个同名局部声明（遮蔽）时，第 n 个（从 0 开始）加上 `#n` 区分；
+/// 用序号而不是偏移，前面的代码改动不会改变标识
+fn shadow_suffix(outline: &Outline, decl: usize) -> String {
+    let path = symbol_path(outline, decl);
+    let nth = (0..decl)
+        .filter(|i| symbol_path(outline, *i) == path)
+        .count();
+
+    if nth == 0 {
+        String::new()
+    } else {
+        format!("#{nth}")
+    }
+}
+
+/// 文件作用域中的声明（以及顶层结构体的字段）可被其他文件导入
+fn is_exported(outline: &Outline, decl: usize) -> bool {
+    let sym = &outline.symbols[decl];
+    match sym.parent {
+        None => sym.scope == 0,
+        Some(parent) => sym.kind == SymbolKind::Field && is_exported(outline, parent),
+    }
+}
+
+/// 光标下符号的标识：本文件的声明按是否导出区分 Export / Local；
+/// 其他文件导入的符号和内置名字为 Import
+pub(crate) fn monikers(
+    uri: &Url,
+    doc: &Document,
+    pos: Position,
+    workspace: &WorkspaceIndex,
+    builtins: &TypeTable,
+) -> Option<Vec<Moniker>> {
+    let text = doc.text.as_str();
+    let (tokens, outline) = (&doc.tokens, &doc.outline);
+    let offset = doc.lines.offset(pos);
+
+    let moniker = |identifier: String, unique, kind| Moniker {
+        scheme: SCHEME.into(),
+        identifier,
+        unique,
+        kind: Some(kind),
+    };
+
+    if let Some(decl) = references::definition_at(text, tokens, outline, offset) {
+        let module = module_path(uri, workspace.root_of(uri));
+        let path = symbol_path(outline, decl).join(".");
+
+        return Some(vec![if is_exported(outline, decl) {
+            moniker(
+                format!("{module}::{path}"),
+                UniquenessLevel::Project,
+                MonikerKind::Export,
+            )
+        } else {
+            moniker(
+                format!("{module}::{path}{}", shadow_suffix(outline, decl)),
+                UniquenessLevel::Document,
+                MonikerKind::Local,
+            )
+        }]);
+    }
+
+    let ident = references::ident_at(tokens, offset)?;
+    let idx = tokens.iter().position(|t| *t == ident)?;
+    if references::is_member(text, tokens, idx) {
+        return None;
+    }
+    let name = ident.text(text);
+
+    // 导入的文件中的顶层符号
+    let imported = workspace
+        .find(uri, name)
+        .into_iter()
+        .find(|(file, _)| workspace.imports_of(uri).contains(file));
+    if let Some((file, _)) = imported {
+        let module = module_path(file, workspace.root_of(file));
+        return Some(vec![moniker(
+            format!("{module}::{name}"),
+            UniquenessLevel::Project,
+            MonikerKind::Import,
+        )]);
+    }
+
+    builtins.var_map.contains_key(name).then(|| {
+        vec![moniker(
+            format!("builtin::{name}"),
+            UniquenessLevel::Scheme,
+            MonikerKind::Import,
+        )]
+    })
+}
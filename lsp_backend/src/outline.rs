@@ -0,0 +1,485 @@
+//! 声明大纲：在扫描结果上识别 import / func / let / const / struct
+//!
+//! 只关心声明和作用域，不做完整语法分析，代码写到一半时也能得到结果。
+
+use crate::syntax::{self, SyntaxToken, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SymbolKind {
+    Variable,
+    Constant,
+    Parameter,
+    Function,
+    Struct,
+    Field,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 名字的字节范围
+    pub name_span: (usize, usize),
+    /// 整个声明的字节范围（函数、结构体包括 `{ .. }`）
+    pub span: (usize, usize),
+    /// 符号所在的作用域，0 为文件作用域
+    pub scope: usize,
+    /// 写出的类型注解；函数为返回类型
+    pub ty: Option<String>,
+    /// 参数所属的函数、字段所属的结构体
+    pub parent: Option<usize>,
+    /// 声明前紧邻的 `///` 文档注释
+    pub doc: Option<String>,
+    /// `let mut` 声明的变量
+    pub mutable: bool,
+}
+
+impl Symbol {
+    /// 文档注释中有以 `@deprecated` 开头的行
+    pub fn is_deprecated(&self) -> bool {
+        self.doc.as_deref().is_some_and(|doc| {
+            doc.lines()
+                .any(|line| line.trim_start().starts_with("@deprecated"))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Scope {
+    /// `{` 到 `}` 的字节范围，文件作用域为整个文本
+    pub span: (usize, usize),
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Import {
+    pub path: String,
+    /// 整条 import 语句的字节范围
+    pub stmt_span: (usize, usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Outline {
+    pub symbols: Vec<Symbol>,
+    pub scopes: Vec<Scope>,
+    pub imports: Vec<Import>,
+}
+
+impl Outline {
+    /// 文件作用域中的声明（即可被其他文件导入的符号）
+    pub fn top_level(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.scope == 0 && s.parent.is_none())
+    }
+
+    pub fn children(&self, parent: usize) -> impl Iterator<Item = &Symbol> {
+        self.symbols
+            .iter()
+            .filter(move |s| s.parent == Some(parent))
+    }
+
+    pub fn declares(&self, name: &str) -> bool {
+        self.symbols
+            .iter()
+            .any(|s| s.name == name && s.kind != SymbolKind::Field)
+    }
+
+    /// 包含偏移的最内层作用域
+    pub fn scope_at(&self, offset: usize) -> usize {
+        // 作用域按 `{` 出现顺序记录，后出现且包含偏移的更靠内
+        self.scopes
+            .iter()
+            .rposition(|s| s.span.0 <= offset && offset < s.span.1)
+            .unwrap_or(0)
+    }
+
+    /// 在 `offset` 处按名字解析到声明，由内向外查找作用域，同一作用域内后声明的遮蔽先声明的
+    pub fn resolve(&self, name: &str, offset: usize) -> Option<usize> {
+        let mut scope = Some(self.scope_at(offset));
+
+        while let Some(current) = scope {
+            let found = self
+                .symbols
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| {
+                    s.scope == current && s.name == name && s.kind != SymbolKind::Field
+                })
+                // 变量声明之后才可见，函数、结构体等在整个作用域内可见
+                .filter(|(_, s)| s.kind != SymbolKind::Variable || s.name_span.0 <= offset)
+                .max_by_key(|(_, s)| s.name_span.0);
+
+            if let Some((idx, _)) = found {
+                return Some(idx);
+            }
+            scope = self.scopes[current].parent;
+        }
+
+        None
+    }
+
+    /// `offset` 处可见的所有声明，内层作用域在前；被遮蔽的同名声明不出现
+    pub fn visible_at(&self, offset: usize) -> Vec<usize> {
+        let mut out: Vec<usize> = vec![];
+        let mut scope = Some(self.scope_at(offset));
+
+        while let Some(current) = scope {
+            let mut found: Vec<_> = self
+                .symbols
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.scope == current && s.kind != SymbolKind::Field)
+                .filter(|(_, s)| s.kind != SymbolKind::Variable || s.name_span.0 <= offset)
+                .collect();
+            // 同一作用域内后声明的在前，先占住名字
+            found.sort_by_key(|(_, s)| std::cmp::Reverse(s.name_span.0));
+
+            for (idx, sym) in found {
+                if !out.iter().any(|it| self.symbols[*it].name == sym.name) {
+                    out.push(idx);
+                }
+            }
+            scope = self.scopes[current].parent;
+        }
+
+        out
+    }
+
+    /// 与函数 `func` 在同一作用域中同名的所有函数（包括自身），按声明顺序
+    pub fn overloads(&self, func: usize) -> Vec<usize> {
+        let target = &self.symbols[func];
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.kind == SymbolKind::Function)
+            .filter(|(_, s)| s.scope == target.scope && s.name == target.name)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// 函数体包含 `offset` 的最内层函数
+    pub fn enclosing_function(&self, offset: usize) -> Option<usize> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.kind == SymbolKind::Function)
+            .filter(|(_, s)| s.span.0 <= offset && offset < s.span.1)
+            .max_by_key(|(_, s)| s.span.0)
+            .map(|(idx, _)| idx)
+    }
+
+    /// 同 [`Outline::resolve`]，但位于声明名字上的 token 总是解析到该声明本身
+    /// （参数名在函数体作用域之外，字段名不参与变量解析）
+    pub fn resolve_at(&self, name: &str, offset: usize) -> Option<usize> {
+        if let Some(idx) = self.symbols.iter().position(|s| s.name_span.0 == offset) {
+            return (self.symbols[idx].kind != SymbolKind::Field).then_some(idx);
+        }
+        self.resolve(name, offset)
+    }
+}
+
+/// 找到与 `open` 下标处括号匹配的闭括号下标
+pub(crate) fn matching(src: &str, tokens: &[SyntaxToken], open: usize) -> Option<usize> {
+    let (l, r) = match tokens.get(open)?.text(src) {
+        "(" => ("(", ")"),
+        "[" => ("[", "]"),
+        "{" => ("{", "}"),
+        _ => return None,
+    };
+
+    let mut depth = 0usize;
+    for (i, t) in tokens.iter().enumerate().skip(open) {
+        if t.kind != TokenKind::Punct {
+            continue;
+        }
+        if t.is(src, l) {
+            depth += 1;
+        } else if t.is(src, r) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// 从 `from` 开始，跳过成对括号，找到第一个位于顶层、文本属于 `stops` 的 token
+pub(crate) fn find_at_depth0(
+    src: &str,
+    tokens: &[SyntaxToken],
+    from: usize,
+    stops: &[&str],
+) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, t) in tokens.iter().enumerate().skip(from) {
+        if t.kind != TokenKind::Punct {
+            continue;
+        }
+        let text = t.text(src);
+        if depth == 0 && stops.contains(&text) {
+            return Some(i);
+        }
+        match text {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth < 0 {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn ident_at(tokens: &[SyntaxToken], i: usize) -> Option<SyntaxToken> {
+    tokens
+        .get(i)
+        .copied()
+        .filter(|t| t.kind == TokenKind::Ident)
+}
+
+/// `tokens[from..to]` 的原文
+fn slice_text(src: &str, tokens: &[SyntaxToken], from: usize, to: usize) -> Option<String> {
+    (from < to && to <= tokens.len())
+        .then(|| src[tokens[from].start..tokens[to - 1].end].to_string())
+}
+
+/// 括号内以逗号（或分号）分隔的一项 `name: Type`
+struct Item {
+    name: SyntaxToken,
+    ty: Option<String>,
+    end: usize,
+}
+
+/// 解析 `open` 处括号内的 `name: Type` 列表，返回各项和闭括号下标
+fn item_list(src: &str, tokens: &[SyntaxToken], open: usize) -> (Vec<Item>, usize) {
+    let close = matching(src, tokens, open).unwrap_or(tokens.len());
+    let mut items = vec![];
+    let mut k = open + 1;
+
+    while k < close {
+        let end = find_at_depth0(src, tokens, k, &[",", ";", ")", "}"])
+            .unwrap_or(close)
+            .min(close);
+
+        if let Some(name) = ident_at(tokens, k) {
+            let ty = tokens
+                .get(k + 1)
+                .filter(|t| t.is(src, ":"))
+                .and_then(|_| slice_text(src, tokens, k + 2, end));
+
+            items.push(Item {
+                name,
+                ty,
+                end: tokens[end - 1].end,
+            });
+        }
+        k = end + 1;
+    }
+
+    (items, close)
+}
+
+/// 紧贴在 `offset` 之前（中间只有空白）的连续 `///` 注释
+fn doc_before(src: &str, all_tokens: &[SyntaxToken], offset: usize) -> Option<String> {
+    let mut lines = vec![];
+    let mut end = offset;
+
+    for t in all_tokens[..all_tokens.partition_point(|t| t.end <= offset)]
+        .iter()
+        .rev()
+    {
+        if t.kind != TokenKind::DocComment || !src[t.end..end].trim().is_empty() {
+            break;
+        }
+        let line = t.text(src).trim_start_matches("///");
+        lines.push(line.strip_prefix(' ').unwrap_or(line));
+        end = t.start;
+    }
+
+    lines.reverse();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+pub(crate) fn outline(src: &str, all_tokens: &[SyntaxToken]) -> Outline {
+    let tokens = syntax::code_tokens(all_tokens);
+
+    let mut out = Outline {
+        scopes: vec![Scope {
+            span: (0, src.len()),
+            parent: None,
+        }],
+        ..Default::default()
+    };
+
+    let mut stack: Vec<usize> = vec![0];
+    // 各作用域对应的函数（函数体闭合时用来补全函数的范围）
+    let mut owners: Vec<Option<usize>> = vec![None];
+    // 等待函数体 `{` 的函数
+    let mut pending_fn: Option<usize> = None;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let tok = tokens[i];
+        let text = tok.text(src);
+        let scope = stack.last().copied().unwrap_or(0);
+
+        let push = |out: &mut Outline, name: SyntaxToken, kind, parent, start: usize| {
+            out.symbols.push(Symbol {
+                name: name.text(src).to_string(),
+                kind,
+                name_span: (name.start, name.end),
+                span: (start, name.end),
+                scope,
+                ty: None,
+                parent,
+                doc: (parent.is_none())
+                    .then(|| doc_before(src, all_tokens, start))
+                    .flatten(),
+                mutable: false,
+            });
+            out.symbols.len() - 1
+        };
+
+        match (tok.kind, text) {
+            (TokenKind::Keyword, "import") => {
+                if let Some(path) = tokens.get(i + 1).filter(|t| t.kind == TokenKind::Str) {
+                    let end = tokens
+                        .get(i + 2)
+                        .filter(|t| t.is(src, ";"))
+                        .map_or(path.end, |t| t.end);
+
+                    out.imports.push(Import {
+                        path: path.text(src).trim_matches('"').to_string(),
+                        stmt_span: (tok.start, end),
+                    });
+                    i += 1;
+                }
+            }
+            (TokenKind::Keyword, "func") => {
+                let Some(name) = ident_at(&tokens, i + 1) else {
+                    i += 1;
+                    continue;
+                };
+                let fn_idx = push(&mut out, name, SymbolKind::Function, None, tok.start);
+                let mut j = i + 2;
+
+                if tokens.get(j).is_some_and(|t| t.is(src, "(")) {
+                    let (params, close) = item_list(src, &tokens, j);
+                    for param in params {
+                        let idx = push(
+                            &mut out,
+                            param.name,
+                            SymbolKind::Parameter,
+                            Some(fn_idx),
+                            param.name.start,
+                        );
+                        out.symbols[idx].ty = param.ty;
+                        out.symbols[idx].span.1 = param.end;
+                    }
+                    j = close + 1;
+                }
+
+                if tokens.get(j).is_some_and(|t| t.is(src, "->")) {
+                    let end =
+                        find_at_depth0(src, &tokens, j + 1, &["{", ";"]).unwrap_or(tokens.len());
+                    out.symbols[fn_idx].ty = slice_text(src, &tokens, j + 1, end);
+                    j = end;
+                }
+
+                if let Some(last) = tokens.get(j.min(tokens.len()) - 1) {
+                    out.symbols[fn_idx].span.1 = last.end;
+                }
+                // 没有函数体（只有声明或写到一半）时参数不挂到后面无关的块上
+                pending_fn = tokens.get(j).filter(|t| t.is(src, "{")).map(|_| fn_idx);
+                i = j;
+                continue;
+            }
+            (TokenKind::Keyword, "let" | "const") => {
+                let mutable = tokens.get(i + 1).is_some_and(|t| t.is(src, "mut"));
+                let name_at = if mutable { i + 2 } else { i + 1 };
+
+                if let Some(name) = ident_at(&tokens, name_at) {
+                    let kind = if text == "const" {
+                        SymbolKind::Constant
+                    } else {
+                        SymbolKind::Variable
+                    };
+                    let idx = push(&mut out, name, kind, None, tok.start);
+                    out.symbols[idx].mutable = mutable;
+
+                    if tokens.get(name_at + 1).is_some_and(|t| t.is(src, ":")) {
+                        let end = find_at_depth0(src, &tokens, name_at + 2, &["=", ";"])
+                            .unwrap_or(tokens.len());
+                        out.symbols[idx].ty = slice_text(src, &tokens, name_at + 2, end);
+                    }
+                    if let Some(end) = find_at_depth0(src, &tokens, name_at, &[";"]) {
+                        out.symbols[idx].span.1 = tokens[end].end;
+                    }
+                    i = name_at;
+                }
+            }
+            (TokenKind::Keyword, "struct") => {
+                if let Some(name) = ident_at(&tokens, i + 1) {
+                    let st_idx = push(&mut out, name, SymbolKind::Struct, None, tok.start);
+
+                    if tokens.get(i + 2).is_some_and(|t| t.is(src, "{")) {
+                        let (fields, close) = item_list(src, &tokens, i + 2);
+                        for field in fields {
+                            let idx = push(
+                                &mut out,
+                                field.name,
+                                SymbolKind::Field,
+                                Some(st_idx),
+                                field.name.start,
+                            );
+                            out.symbols[idx].ty = field.ty;
+                            out.symbols[idx].span.1 = field.end;
+                        }
+                        out.symbols[st_idx].span.1 = tokens.get(close).map_or(src.len(), |t| t.end);
+                        i = close;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            (TokenKind::Punct, "{") => {
+                let idx = out.scopes.len();
+                // 未闭合的块一直延伸到文本末尾
+                out.scopes.push(Scope {
+                    span: (tok.start, src.len()),
+                    parent: Some(scope),
+                });
+
+                let owner = pending_fn.take();
+                // 参数属于函数体作用域
+                if let Some(fn_idx) = owner {
+                    for sym in out.symbols.iter_mut().filter(|s| s.parent == Some(fn_idx)) {
+                        sym.scope = idx;
+                    }
+                    out.symbols[fn_idx].span.1 = src.len();
+                }
+                owners.push(owner);
+                stack.push(idx);
+            }
+            (TokenKind::Punct, "}") if stack.len() > 1 => {
+                if let Some(idx) = stack.pop() {
+                    out.scopes[idx].span.1 = tok.end;
+
+                    if let Some(fn_idx) = owners[idx] {
+                        out.symbols[fn_idx].span.1 = tok.end;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    out
+}
@@ -0,0 +1,73 @@
+//! 服务器发起的 work done progress（`window/workDoneProgress/create` 和 `$/progress`）
+
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::WorkDoneProgressCreate;
+use tower_lsp::lsp_types::*;
+
+use crate::Backend;
+
+impl Backend {
+    /// 开始一个进度，客户端不支持或拒绝创建时返回 None，之后的报告都不发送
+    pub(crate) async fn begin_progress(&self, title: &str) -> Option<ProgressToken> {
+        let supported = self
+            .client_capabilities
+            .read()
+            .await
+            .window
+            .as_ref()
+            .and_then(|it| it.work_done_progress)
+            .unwrap_or(false);
+        if !supported {
+            return None;
+        }
+
+        let token = NumberOrString::String(format!("typedAnt/{title}"));
+        self.client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .ok()?;
+
+        let begin = WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.into(),
+            percentage: Some(0),
+            ..Default::default()
+        });
+        self.send_progress(&token, begin).await;
+        Some(token)
+    }
+
+    pub(crate) async fn report_progress(
+        &self,
+        token: Option<&ProgressToken>,
+        message: String,
+        percentage: u32,
+    ) {
+        let Some(token) = token else {
+            return;
+        };
+        let report = WorkDoneProgress::Report(WorkDoneProgressReport {
+            message: Some(message),
+            percentage: Some(percentage),
+            ..Default::default()
+        });
+        self.send_progress(token, report).await;
+    }
+
+    pub(crate) async fn end_progress(&self, token: Option<ProgressToken>) {
+        if let Some(token) = token {
+            let end = WorkDoneProgress::End(WorkDoneProgressEnd { message: None });
+            self.send_progress(&token, end).await;
+        }
+    }
+
+    async fn send_progress(&self, token: &ProgressToken, value: WorkDoneProgress) {
+        self.client
+            .send_notification::<Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(value),
+            })
+            .await;
+    }
+}
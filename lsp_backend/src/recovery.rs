@@ -0,0 +1,109 @@
+//! 语法错误恢复：修补文本后重新交给上游 parser
+//!
+//! 上游 parser 在第一个错误处放弃。编辑过程中的代码经常写到一半（`let x = `），
+//! 这里跳过出错的语句、补上缺少的闭括号，让后面的代码仍能得到分析。
+//! 跳过的语句替换成等长的空白，修补前后其余代码的字节偏移不变。
+
+use crate::syntax::{self, SyntaxToken, TokenKind};
+
+/// 最多修补的次数，每次修补处理一个语法错误
+pub(crate) const MAX_RECOVERIES: usize = 16;
+
+/// 可以开始一条新语句的关键字
+const STMT_KEYWORDS: &[&str] = &[
+    "let", "const", "func", "struct", "import", "return", "if", "while", "for", "break", "continue",
+];
+
+/// 把 `text[start..end]` 换成等长的空白，保留换行
+fn blank(text: &str, start: usize, end: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    out.push_str(&text[..start]);
+    for c in text[start..end].chars() {
+        match c {
+            '\n' | '\r' => out.push(c),
+            _ => out.extend(std::iter::repeat_n(' ', c.len_utf8())),
+        }
+    }
+    out.push_str(&text[end..]);
+    out
+}
+
+/// 按出现顺序未闭合的括号对应的闭括号，最内层的在前
+fn unclosed(src: &str, tokens: &[SyntaxToken]) -> Vec<&'static str> {
+    let mut stack = vec![];
+    for t in tokens.iter().filter(|t| t.kind == TokenKind::Punct) {
+        match t.text(src) {
+            "(" => stack.push(")"),
+            "[" => stack.push("]"),
+            "{" => stack.push("}"),
+            close @ (")" | "]" | "}") => {
+                if stack.last() == Some(&close) {
+                    stack.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+    stack.reverse();
+    stack
+}
+
+/// 包含 `tokens[idx]` 的语句的 token 范围 `[start, end)`：
+/// 从前一个 `;`、`{`、`}` 之后开始，到顶层的 `;`（包括）、所在块的 `}`，
+/// 或出错位置之后另起一条语句的关键字为止
+fn statement_at(src: &str, tokens: &[SyntaxToken], idx: usize) -> (usize, usize) {
+    let start = tokens[..idx]
+        .iter()
+        .rposition(|t| matches!(t.text(src), ";" | "{" | "}"))
+        .map_or(0, |i| i + 1);
+
+    let mut depth = 0i32;
+    for (i, t) in tokens.iter().enumerate().skip(start) {
+        let text = t.text(src);
+        if depth == 0 && i >= idx && i > start {
+            if t.kind == TokenKind::Keyword && STMT_KEYWORDS.contains(&text) {
+                return (start, i);
+            }
+            if text == ";" {
+                return (start, i + 1);
+            }
+        }
+        match text {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth < 0 {
+                    return (start, i);
+                }
+            }
+            _ => {}
+        }
+    }
+    (start, tokens.len())
+}
+
+/// 修补 `offset` 处的语法错误，无法继续修补时返回 None。
+/// 错误在文本末尾且有未闭合的括号时补上闭括号；否则跳过出错的语句，
+/// 语句为空时只跳过出错的 token
+pub(crate) fn patch(text: &str, offset: usize) -> Option<String> {
+    let tokens = syntax::code_tokens(&syntax::scan(text));
+    let last = tokens.last()?;
+
+    if offset >= last.end {
+        let closers = unclosed(text, &tokens);
+        if !closers.is_empty() {
+            return Some(format!("{text}\n{}", closers.concat()));
+        }
+    }
+
+    let idx = tokens
+        .partition_point(|t| t.end <= offset)
+        .min(tokens.len() - 1);
+    let (start, end) = match statement_at(text, &tokens, idx) {
+        (start, end) if start < end => (start, end),
+        _ => (idx, idx + 1),
+    };
+
+    let patched = blank(text, tokens[start].start, tokens[end - 1].end);
+    (patched != text).then_some(patched)
+}
@@ -0,0 +1,57 @@
+//! 基于作用域的引用查找
+
+use crate::outline::Outline;
+use crate::syntax::{SyntaxToken, TokenKind};
+
+/// 光标处（包括紧贴在标识符末尾）的标识符 token
+pub(crate) fn ident_at(tokens: &[SyntaxToken], offset: usize) -> Option<SyntaxToken> {
+    let idx = tokens.partition_point(|t| t.end < offset);
+
+    tokens[idx..]
+        .iter()
+        .take(2)
+        .find(|t| t.kind == TokenKind::Ident && t.start <= offset && offset <= t.end)
+        .copied()
+}
+
+/// 成员访问 `a.b` 中的 `b` 不是变量引用
+pub(crate) fn is_member(src: &str, tokens: &[SyntaxToken], idx: usize) -> bool {
+    idx.checked_sub(1)
+        .and_then(|i| tokens.get(i))
+        .is_some_and(|t| t.is(src, "."))
+}
+
+/// 光标下标识符解析到的声明
+pub(crate) fn definition_at(
+    src: &str,
+    tokens: &[SyntaxToken],
+    outline: &Outline,
+    offset: usize,
+) -> Option<usize> {
+    let ident = ident_at(tokens, offset)?;
+    let idx = tokens.iter().position(|t| *t == ident)?;
+
+    if is_member(src, tokens, idx) {
+        return None;
+    }
+    outline.resolve_at(ident.text(src), ident.start)
+}
+
+/// 所有解析到声明 `decl` 的标识符（包括声明本身）的字节范围
+pub(crate) fn references(
+    src: &str,
+    tokens: &[SyntaxToken],
+    outline: &Outline,
+    decl: usize,
+) -> Vec<(usize, usize)> {
+    let name = outline.symbols[decl].name.as_str();
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.kind == TokenKind::Ident && t.is(src, name))
+        .filter(|(i, _)| !is_member(src, tokens, *i))
+        .filter(|(_, t)| outline.resolve_at(name, t.start) == Some(decl))
+        .map(|(_, t)| (t.start, t.end))
+        .collect()
+}
@@ -0,0 +1,115 @@
+//! 把诊断渲染成 rustc 风格的终端文本：标题、位置、源码行和 `^^^` 下划线
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::line_index::LineIndex;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+fn severity_style(severity: Option<DiagnosticSeverity>) -> (&'static str, &'static str) {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) | None => ("error", "\x1b[1;31m"),
+        Some(DiagnosticSeverity::WARNING) => ("warning", "\x1b[1;33m"),
+        Some(DiagnosticSeverity::INFORMATION) => ("info", "\x1b[1;36m"),
+        _ => ("hint", "\x1b[1;36m"),
+    }
+}
+
+/// 关闭颜色时所有样式都为空
+struct Paint(bool);
+
+impl Paint {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.0 {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// 下划线前的缩进：制表符保留为制表符，其他字符（按字符计）换成空格，保证与源码行对齐
+fn padding(prefix: &str) -> String {
+    prefix
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+/// 渲染一条诊断。`source` 是诊断所在文件的全文，`path` 显示在位置行中；
+/// 范围跨多行时每行都画出下划线。`color` 为假时不输出 ANSI 转义序列，适合管道和文件
+pub fn render_diagnostic(diag: &Diagnostic, source: &str, path: &str, color: bool) -> String {
+    let paint = Paint(color);
+    let lines = LineIndex::new(source);
+    let (label, style) = severity_style(diag.severity);
+
+    let code = match &diag.code {
+        Some(NumberOrString::String(code)) => format!("[{code}]"),
+        Some(NumberOrString::Number(code)) => format!("[{code}]"),
+        None => String::new(),
+    };
+
+    let start = lines.offset(diag.range.start);
+    let end = lines.offset(diag.range.end).max(start);
+    let (first, last) = (diag.range.start.line as usize, diag.range.end.line as usize);
+    let width = (last + 1).to_string().len();
+    let gutter = |text: &str| paint.paint(BLUE, &format!("{text:>width$} |"));
+
+    let mut out = format!(
+        "{}{}\n",
+        paint.paint(style, &format!("{label}{code}")),
+        paint.paint(BOLD, &format!(": {}", diag.message)),
+    );
+    out.push_str(&format!(
+        "{}{path}:{}:{}\n",
+        paint.paint(BLUE, &format!("{:width$}--> ", "")),
+        diag.range.start.line + 1,
+        diag.range.start.character + 1,
+    ));
+    out.push_str(&gutter(""));
+    out.push('\n');
+
+    for line in first..=last {
+        let Some((line_start, line_end)) = lines.line_span(line) else {
+            break;
+        };
+        // 范围结束在下一行行首时，那一行并没有被覆盖
+        if line > first && end == line_start {
+            break;
+        }
+        let text = source[line_start..line_end].trim_end_matches('\r');
+        out.push_str(&format!("{} {text}\n", gutter(&(line + 1).to_string())));
+
+        // 这一行中被覆盖的部分，空范围也至少画一个 `^`
+        let from = start.clamp(line_start, line_start + text.len()) - line_start;
+        let to = end.clamp(line_start, line_start + text.len()) - line_start;
+        let carets = text[from..to].chars().count().max(1);
+        out.push_str(&format!(
+            "{} {}{}\n",
+            gutter(""),
+            padding(&text[..from]),
+            paint.paint(style, &"^".repeat(carets)),
+        ));
+    }
+
+    for related in diag.related_information.iter().flatten() {
+        let at = related.location.range.start;
+        let uri = &related.location.uri;
+        let file = uri
+            .to_file_path()
+            .map_or(uri.to_string(), |it| it.display().to_string());
+        out.push_str(&format!(
+            "{} {}: {} ({}:{}:{})\n",
+            paint.paint(BLUE, &format!("{:width$} =", "")),
+            paint.paint(BOLD, "note"),
+            related.message,
+            file,
+            at.line + 1,
+            at.character + 1,
+        ));
+    }
+
+    out
+}
@@ -0,0 +1,70 @@
+//! 按行列位置查询作用域：光标处由内向外的作用域链，以及按名字解析到的声明。
+//! 是 `Outline` 按位置查询的公开接口；位置处可见的所有符号见 [`crate::visible_symbols_at`]
+
+use tower_lsp::lsp_types::{Position, Range, SymbolKind};
+
+use crate::document::Document;
+use crate::line_index::LineIndex;
+use crate::outline::Outline;
+use crate::symbols::lsp_kind;
+
+/// 一个作用域中引入的声明
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopedSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 写出的类型注解（函数为返回类型）
+    pub ty: Option<String>,
+    /// 声明中名字的范围
+    pub name_range: Range,
+    /// 声明所在作用域的范围，文件作用域为整个文档
+    pub scope_range: Range,
+}
+
+/// 文档的作用域树，借用文档的大纲，随文档一起重建
+#[derive(Debug, Clone, Copy)]
+pub struct ScopeTree<'a> {
+    outline: &'a Outline,
+    lines: &'a LineIndex,
+}
+
+impl Document {
+    pub fn scope_tree(&self) -> ScopeTree<'_> {
+        ScopeTree {
+            outline: &self.outline,
+            lines: &self.lines,
+        }
+    }
+}
+
+impl ScopeTree<'_> {
+    fn symbol(&self, idx: usize) -> ScopedSymbol {
+        let sym = &self.outline.symbols[idx];
+        ScopedSymbol {
+            name: sym.name.clone(),
+            kind: lsp_kind(sym.kind),
+            ty: sym.ty.clone(),
+            name_range: self.lines.range(sym.name_span),
+            scope_range: self.lines.range(self.outline.scopes[sym.scope].span),
+        }
+    }
+
+    /// 包含 `pos` 的作用域的范围，由内向外，最后一个是文件作用域
+    pub fn scopes_at(&self, pos: Position) -> Vec<Range> {
+        let mut out = vec![];
+        let mut scope = Some(self.outline.scope_at(self.lines.offset(pos)));
+
+        while let Some(current) = scope {
+            out.push(self.lines.range(self.outline.scopes[current].span));
+            scope = self.outline.scopes[current].parent;
+        }
+        out
+    }
+
+    /// 在 `pos` 处按名字解析到的声明：由内向外查找，内层的遮蔽外层的，变量声明之后才可见
+    pub fn resolve(&self, pos: Position, name: &str) -> Option<ScopedSymbol> {
+        self.outline
+            .resolve(name, self.lines.offset(pos))
+            .map(|idx| self.symbol(idx))
+    }
+}
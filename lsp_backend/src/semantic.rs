@@ -0,0 +1,163 @@
+//! 语义高亮：在扫描结果上按声明大纲区分函数、结构体、参数、变量和字段
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::outline::{Symbol, SymbolKind};
+use crate::syntax::{self, SyntaxToken, TokenKind};
+
+/// 与 [`legend`] 中的顺序一致
+const KEYWORD: u32 = 0;
+const VARIABLE: u32 = 1;
+const PARAMETER: u32 = 2;
+const FUNCTION: u32 = 3;
+const STRUCT: u32 = 4;
+const PROPERTY: u32 = 5;
+const NUMBER: u32 = 6;
+const STRING: u32 = 7;
+const COMMENT: u32 = 8;
+
+const DECLARATION: u32 = 1 << 0;
+const READONLY: u32 = 1 << 1;
+const DOCUMENTATION: u32 = 1 << 2;
+
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::KEYWORD,
+            SemanticTokenType::VARIABLE,
+            SemanticTokenType::PARAMETER,
+            SemanticTokenType::FUNCTION,
+            SemanticTokenType::STRUCT,
+            SemanticTokenType::PROPERTY,
+            SemanticTokenType::NUMBER,
+            SemanticTokenType::STRING,
+            SemanticTokenType::COMMENT,
+        ],
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::READONLY,
+            SemanticTokenModifier::DOCUMENTATION,
+        ],
+    }
+}
+
+/// 代码 token `tokens[i]` 的类型和修饰；不需要高亮的（标点、无法解析的名字）返回 None。
+/// `decls` 是按名字起点索引的声明
+fn classify(
+    doc: &Document,
+    tokens: &[SyntaxToken],
+    decls: &HashMap<usize, &Symbol>,
+    i: usize,
+) -> Option<(u32, u32)> {
+    let (src, outline) = (doc.text.as_str(), &doc.outline);
+    let tok = tokens[i];
+
+    match tok.kind {
+        TokenKind::Keyword => return Some((KEYWORD, 0)),
+        TokenKind::Number => return Some((NUMBER, 0)),
+        TokenKind::Str => return Some((STRING, 0)),
+        TokenKind::Ident => {}
+        _ => return None,
+    }
+
+    let text = |k: Option<usize>| k.and_then(|k| tokens.get(k)).map_or("", |t| t.text(src));
+    let prev = text(i.checked_sub(1));
+    let decl = decls.get(&tok.start).copied();
+
+    // 成员访问，以及结构体字面量中的字段名
+    let member = prev == "." || (matches!(prev, "{" | ",") && text(Some(i + 1)) == ":");
+    if member && decl.is_none() {
+        return Some((PROPERTY, 0));
+    }
+
+    let (sym, modifiers) = match decl {
+        Some(decl) => (decl, DECLARATION),
+        None => (
+            &outline.symbols[outline.resolve(tok.text(src), tok.start)?],
+            0,
+        ),
+    };
+
+    Some(match sym.kind {
+        SymbolKind::Variable => (VARIABLE, modifiers),
+        SymbolKind::Constant => (VARIABLE, modifiers | READONLY),
+        SymbolKind::Parameter => (PARAMETER, modifiers),
+        SymbolKind::Function => (FUNCTION, modifiers),
+        SymbolKind::Struct => (STRUCT, modifiers),
+        SymbolKind::Field => (PROPERTY, modifiers),
+    })
+}
+
+/// `span` 内（None 为整个文档）的语义 token，只计算与范围相交的 token。
+/// 相对编码总是从 (0, 0) 开始，所以范围结果的第一个 token 给出的是绝对位置
+pub(crate) fn semantic_tokens(doc: &Document, span: Option<(usize, usize)>) -> Vec<SemanticToken> {
+    let src = doc.text.as_str();
+    let (from, to) = span.unwrap_or((0, src.len()));
+    let in_span = |t: &SyntaxToken| t.end > from && t.start < to;
+
+    let mut classified: Vec<(SyntaxToken, u32, u32)> = doc
+        .tokens
+        .iter()
+        .filter(|t| in_span(t))
+        .filter_map(|t| match t.kind {
+            TokenKind::Comment => Some((*t, COMMENT, 0)),
+            TokenKind::DocComment => Some((*t, COMMENT, DOCUMENTATION)),
+            _ => None,
+        })
+        .collect();
+
+    let tokens = syntax::code_tokens(&doc.tokens);
+    // 起点相同时保留大纲中靠前的声明
+    let decls: HashMap<_, _> = doc
+        .outline
+        .symbols
+        .iter()
+        .rev()
+        .map(|s| (s.name_span.0, s))
+        .collect();
+    let first = tokens.partition_point(|t| t.end <= from);
+    for (i, tok) in tokens.iter().enumerate().skip(first) {
+        if !in_span(tok) {
+            break;
+        }
+        if let Some((ty, modifiers)) = classify(doc, &tokens, &decls, i) {
+            classified.push((*tok, ty, modifiers));
+        }
+    }
+    classified.sort_by_key(|(t, _, _)| t.start);
+
+    let mut out = vec![];
+    let mut prev = Position::new(0, 0);
+
+    for (tok, ty, modifiers) in classified {
+        // 跨行的字符串、块注释按行拆开
+        let mut start = tok.start;
+        for line in tok.text(src).split('\n') {
+            let piece = line.strip_suffix('\r').unwrap_or(line);
+            let pos = doc.lines.position(start);
+            start += line.len() + 1;
+            if piece.is_empty() {
+                continue;
+            }
+
+            let delta_line = pos.line - prev.line;
+            out.push(SemanticToken {
+                delta_line,
+                delta_start: if delta_line == 0 {
+                    pos.character - prev.character
+                } else {
+                    pos.character
+                },
+                length: doc.lines.width(piece) as u32,
+                token_type: ty,
+                token_modifiers_bitset: modifiers,
+            });
+            prev = pos;
+        }
+    }
+
+    out
+}
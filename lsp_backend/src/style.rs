@@ -0,0 +1,165 @@
+//! 风格检查：只给出 HINT，不计入错误数
+
+use tower_lsp::lsp_types::*;
+
+use crate::checks::{self, CheckContext};
+use crate::outline::{self, SymbolKind};
+use crate::syntax::TokenKind;
+
+pub(crate) const NAMING_CONVENTION: &str = "naming-convention";
+pub(crate) const REDUNDANT_PARENS: &str = "redundant-parens";
+pub(crate) const REDUNDANT_ANNOTATION: &str = "redundant-annotation";
+
+pub(crate) fn run(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let settings = &ctx.settings.style;
+    if !settings.enabled {
+        return;
+    }
+
+    let mut hints = vec![];
+    if settings.naming {
+        naming(ctx, &mut hints);
+    }
+    if settings.redundant_parens {
+        redundant_parens(ctx, &mut hints);
+    }
+    if settings.redundant_annotations {
+        redundant_annotations(ctx, &mut hints);
+    }
+
+    for hint in &mut hints {
+        hint.severity = Some(DiagnosticSeverity::HINT);
+    }
+    out.extend(hints);
+}
+
+fn is_snake_case(name: &str) -> bool {
+    name.trim_start_matches('_')
+        .chars()
+        .all(|c| !c.is_uppercase())
+}
+
+fn is_upper_camel_case(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_uppercase) && !name.contains('_')
+}
+
+fn is_upper_snake_case(name: &str) -> bool {
+    name.chars().all(|c| !c.is_lowercase())
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if i > 0 && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// 函数、变量、参数、字段用 snake_case，结构体用 UpperCamelCase，常量用 UPPER_SNAKE_CASE
+fn naming(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    for sym in &ctx.outline.symbols {
+        let name = sym.name.as_str();
+
+        let (ok, expected) = match sym.kind {
+            SymbolKind::Struct => (is_upper_camel_case(name), "UpperCamelCase"),
+            SymbolKind::Constant => (is_upper_snake_case(name), "UPPER_SNAKE_CASE"),
+            _ => (is_snake_case(name), "snake_case"),
+        };
+        if ok {
+            continue;
+        }
+
+        let message = match sym.kind {
+            SymbolKind::Struct | SymbolKind::Constant => {
+                format!("`{name}` should be written in {expected}")
+            }
+            _ => format!(
+                "`{name}` should be written in {expected}: `{}`",
+                to_snake_case(name)
+            ),
+        };
+        out.push(ctx.diagnostic(sym.name_span, NAMING_CONVENTION, message));
+    }
+}
+
+/// `if (x) {`、`while (x) {`、`return (x);` 中多余的括号
+fn redundant_parens(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for (i, tok) in tokens.iter().enumerate() {
+        if tok.kind != TokenKind::Keyword || !matches!(tok.text(src), "if" | "while" | "return") {
+            continue;
+        }
+        if !tokens.get(i + 1).is_some_and(|t| t.is(src, "(")) {
+            continue;
+        }
+        let Some(close) = outline::matching(src, tokens, i + 1) else {
+            continue;
+        };
+
+        // 括号必须包住整个条件 / 返回值
+        let end = if tok.is(src, "return") { ";" } else { "{" };
+        if !tokens.get(close + 1).is_some_and(|t| t.is(src, end)) {
+            continue;
+        }
+
+        let keyword = tok.text(src);
+        out.push(ctx.diagnostic(
+            (tokens[i + 1].start, tokens[close].end),
+            REDUNDANT_PARENS,
+            format!("unnecessary parentheses around `{keyword}` expression"),
+        ));
+    }
+}
+
+/// `let x: T = y;` 中初始值的类型已经能从源码读出且与注解相同。
+/// 诊断范围是要删除的 `: T`，代码操作直接删掉这段
+fn redundant_annotations(ctx: &CheckContext, out: &mut Vec<Diagnostic>) {
+    let (src, tokens) = (ctx.src(), ctx.tokens);
+
+    for sym in &ctx.outline.symbols {
+        if !matches!(sym.kind, SymbolKind::Variable | SymbolKind::Constant) {
+            continue;
+        }
+        let Some(ty) = sym.ty.as_deref() else {
+            continue;
+        };
+
+        let name = tokens.partition_point(|t| t.start < sym.name_span.0);
+        if !tokens.get(name + 1).is_some_and(|t| t.is(src, ":")) {
+            continue;
+        }
+        let Some(eq) = outline::find_at_depth0(src, tokens, name + 2, &["=", ";"])
+            .filter(|eq| tokens[*eq].is(src, "="))
+        else {
+            continue;
+        };
+        let Some(semi) = outline::find_at_depth0(src, tokens, eq + 1, &[";"]) else {
+            continue;
+        };
+
+        // 初始值引用了同名的旧变量时，按名字解析会找到这个声明本身
+        let init = &tokens[eq + 1..semi];
+        if init.is_empty() || init.iter().any(|t| t.is(src, &sym.name)) {
+            continue;
+        }
+        if checks::written_type(ctx, eq + 1, semi) != Some(ty) {
+            continue;
+        }
+
+        let mut diag = ctx.diagnostic(
+            (tokens[name].end, tokens[eq - 1].end),
+            REDUNDANT_ANNOTATION,
+            format!("type annotation is redundant, the value already has type `{ty}`"),
+        );
+        diag.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+        out.push(diag);
+    }
+}
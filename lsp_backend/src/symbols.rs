@@ -0,0 +1,114 @@
+use ant_type_checker::table::TypeTable;
+use tower_lsp::lsp_types::*;
+
+use crate::document::Document;
+use crate::outline::{Outline, Symbol, SymbolKind};
+
+/// 某个位置可见的符号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: tower_lsp::lsp_types::SymbolKind,
+    /// 写出的类型注解（函数为返回类型）；内置名字为类型检查器给出的类型
+    pub ty: Option<String>,
+}
+
+pub(crate) fn lsp_kind(kind: SymbolKind) -> tower_lsp::lsp_types::SymbolKind {
+    use tower_lsp::lsp_types::SymbolKind as Lsp;
+
+    match kind {
+        SymbolKind::Variable | SymbolKind::Parameter => Lsp::VARIABLE,
+        SymbolKind::Constant => Lsp::CONSTANT,
+        SymbolKind::Function => Lsp::FUNCTION,
+        SymbolKind::Struct => Lsp::STRUCT,
+        SymbolKind::Field => Lsp::FIELD,
+    }
+}
+
+/// 按源码位置排序，编辑器可能按返回顺序直接显示
+fn sorted<'a>(symbols: impl Iterator<Item = (usize, &'a Symbol)>) -> Vec<(usize, &'a Symbol)> {
+    let mut symbols: Vec<_> = symbols.collect();
+    symbols.sort_by_key(|(_, s)| s.span.0);
+    symbols
+}
+
+#[allow(deprecated)]
+fn document_symbol(doc: &Document, outline: &Outline, idx: usize) -> DocumentSymbol {
+    let sym = &outline.symbols[idx];
+    let children: Vec<_> = sorted(
+        outline
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.parent == Some(idx)),
+    )
+    .into_iter()
+    .map(|(child, _)| document_symbol(doc, outline, child))
+    .collect();
+
+    DocumentSymbol {
+        name: sym.name.clone(),
+        detail: sym.ty.clone(),
+        kind: lsp_kind(sym.kind),
+        tags: sym.is_deprecated().then(|| vec![SymbolTag::DEPRECATED]),
+        deprecated: None,
+        range: doc.lines.range(sym.span),
+        selection_range: doc.lines.range(sym.name_span),
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+impl Document {
+    /// 文件作用域中的声明，参数和字段作为子项，都按声明顺序排列
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let outline = &self.outline;
+
+        let top = outline
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.scope == 0 && s.parent.is_none());
+
+        sorted(top)
+            .into_iter()
+            .map(|(idx, _)| document_symbol(self, outline, idx))
+            .collect()
+    }
+}
+
+/// `offset` 处可见的变量、函数和类型：由内向外的本文件声明，之后是未被遮蔽的内置名字
+pub(crate) fn visible_symbols(
+    doc: &Document,
+    offset: usize,
+    builtins: &TypeTable,
+) -> Vec<SymbolInfo> {
+    let outline = &doc.outline;
+
+    let mut out: Vec<_> = outline
+        .visible_at(offset)
+        .into_iter()
+        .map(|idx| {
+            let sym = &outline.symbols[idx];
+            SymbolInfo {
+                name: sym.name.clone(),
+                kind: lsp_kind(sym.kind),
+                ty: sym.ty.clone(),
+            }
+        })
+        .collect();
+
+    let mut extra: Vec<_> = builtins
+        .var_map
+        .iter()
+        .map(|(name, ty)| SymbolInfo {
+            name: name.to_string(),
+            kind: tower_lsp::lsp_types::SymbolKind::VARIABLE,
+            ty: Some(ty.to_string()),
+        })
+        .filter(|it| !out.iter().any(|s| s.name == it.name))
+        .collect();
+    extra.sort_by(|a, b| a.name.cmp(&b.name));
+
+    out.extend(extra);
+    out
+}
@@ -0,0 +1,133 @@
+//! LSP 侧的轻量词法扫描
+//!
+//! 编辑过程中的代码经常是不完整的，上游 lexer 遇到错误就会放弃。
+//! 这里的扫描器永不失败，并且保留注释，供补全、代码操作等功能使用。
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Ident,
+    Keyword,
+    Number,
+    Str,
+    Comment,
+    /// `///` 文档注释
+    DocComment,
+    Punct,
+}
+
+/// 按字节偏移记录位置的 token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SyntaxToken {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SyntaxToken {
+    pub fn text<'a>(&self, src: &'a str) -> &'a str {
+        &src[self.start..self.end]
+    }
+
+    pub fn is(&self, src: &str, text: &str) -> bool {
+        self.text(src) == text
+    }
+}
+
+pub(crate) const KEYWORDS: &[&str] = &[
+    "let", "mut", "const", "func", "return", "if", "else", "while", "for", "in", "break",
+    "continue", "struct", "import", "true", "false",
+];
+
+/// 多字符运算符，较长的放在前面
+const OPERATORS: &[&str] = &[
+    "->", "==", "!=", "<=", ">=", "&&", "||", "::", "+=", "-=", "*=", "/=", "%=",
+];
+
+pub(crate) fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 扫描整段文本，跳过空白
+pub(crate) fn scan(text: &str) -> Vec<SyntaxToken> {
+    let mut tokens = Vec::new();
+    let bytes = text.as_bytes();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = if c.is_whitespace() {
+            continue;
+        } else if text[start..].starts_with("//") {
+            while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+
+            let comment = &text[start..];
+            if comment.starts_with("///") && !comment.starts_with("////") {
+                TokenKind::DocComment
+            } else {
+                TokenKind::Comment
+            }
+        } else if text[start..].starts_with("/*") {
+            chars.next();
+            let mut prev = '\0';
+            for (_, c) in chars.by_ref() {
+                if prev == '*' && c == '/' {
+                    break;
+                }
+                prev = c;
+            }
+            TokenKind::Comment
+        } else if c == '"' {
+            let mut escaped = false;
+            for (_, c) in chars.by_ref() {
+                match c {
+                    '\\' if !escaped => escaped = true,
+                    '"' if !escaped => break,
+                    _ => escaped = false,
+                }
+            }
+            TokenKind::Str
+        } else if c.is_ascii_digit() {
+            while chars
+                .next_if(|(i, c)| {
+                    is_ident_char(*c)
+                        || (*c == '.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+                })
+                .is_some()
+            {}
+            TokenKind::Number
+        } else if is_ident_start(c) {
+            while chars.next_if(|(_, c)| is_ident_char(*c)).is_some() {}
+
+            let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+            if KEYWORDS.contains(&&text[start..end]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Ident
+            }
+        } else {
+            if let Some(op) = OPERATORS.iter().find(|op| text[start..].starts_with(**op)) {
+                for _ in 1..op.len() {
+                    chars.next();
+                }
+            }
+            TokenKind::Punct
+        };
+
+        let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+        tokens.push(SyntaxToken { kind, start, end });
+    }
+
+    tokens
+}
+
+/// 去掉注释后的 token
+pub(crate) fn code_tokens(tokens: &[SyntaxToken]) -> Vec<SyntaxToken> {
+    tokens
+        .iter()
+        .copied()
+        .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::DocComment))
+        .collect()
+}
@@ -0,0 +1,135 @@
+//! `typedAnt/ast` 返回的语法树：按范围嵌套的声明、import 和代码块，加上所有 token
+
+use serde::Serialize;
+use tower_lsp::lsp_types::Range;
+
+use crate::document::Document;
+use crate::outline::SymbolKind;
+use crate::syntax::TokenKind;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyntaxNode {
+    /// `file`、`import`、`block`，或声明的种类（`function`、`variable` 等）
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 写出的类型注解，函数为返回类型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    pub range: Range,
+    /// 声明中名字的范围
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_range: Option<Range>,
+    pub children: Vec<SyntaxNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyntaxTokenInfo {
+    pub kind: &'static str,
+    pub range: Range,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    /// 所有 token（包括注释），按出现顺序
+    pub tokens: Vec<SyntaxTokenInfo>,
+}
+
+fn symbol_kind(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Variable => "variable",
+        SymbolKind::Constant => "constant",
+        SymbolKind::Parameter => "parameter",
+        SymbolKind::Function => "function",
+        SymbolKind::Struct => "struct",
+        SymbolKind::Field => "field",
+    }
+}
+
+fn token_kind(kind: TokenKind) -> &'static str {
+    match kind {
+        TokenKind::Ident => "ident",
+        TokenKind::Keyword => "keyword",
+        TokenKind::Number => "number",
+        TokenKind::Str => "string",
+        TokenKind::Comment => "comment",
+        TokenKind::DocComment => "docComment",
+        TokenKind::Punct => "punct",
+    }
+}
+
+/// 按字节范围排好序的节点，外层的在前
+struct Flat {
+    span: (usize, usize),
+    node: SyntaxNode,
+}
+
+pub(crate) fn syntax_tree(doc: &Document) -> SyntaxTree {
+    let outline = &doc.outline;
+    let lines = &doc.lines;
+    let node = |kind, span, name: Option<String>| SyntaxNode {
+        kind,
+        name,
+        ty: None,
+        range: lines.range(span),
+        name_range: None,
+        children: vec![],
+    };
+
+    let mut flat: Vec<Flat> = vec![];
+    for import in &outline.imports {
+        flat.push(Flat {
+            span: import.stmt_span,
+            node: node("import", import.stmt_span, Some(import.path.clone())),
+        });
+    }
+    for scope in outline.scopes.iter().skip(1) {
+        flat.push(Flat {
+            span: scope.span,
+            node: node("block", scope.span, None),
+        });
+    }
+    for sym in &outline.symbols {
+        let mut it = node(symbol_kind(sym.kind), sym.span, Some(sym.name.clone()));
+        it.ty = sym.ty.clone();
+        it.name_range = Some(lines.range(sym.name_span));
+        flat.push(Flat {
+            span: sym.span,
+            node: it,
+        });
+    }
+    flat.sort_by_key(|it| (it.span.0, std::cmp::Reverse(it.span.1)));
+
+    // 依次挂到栈中最近的包含它的节点下
+    let mut stack = vec![Flat {
+        span: (0, doc.text.len()),
+        node: node("file", (0, doc.text.len()), None),
+    }];
+    for item in flat {
+        while stack.len() > 1 && stack.last().is_some_and(|it| item.span.0 >= it.span.1) {
+            let done = stack.pop().unwrap();
+            stack.last_mut().unwrap().node.children.push(done.node);
+        }
+        stack.push(item);
+    }
+    while stack.len() > 1 {
+        let done = stack.pop().unwrap();
+        stack.last_mut().unwrap().node.children.push(done.node);
+    }
+
+    let tokens = doc
+        .tokens
+        .iter()
+        .map(|t| SyntaxTokenInfo {
+            kind: token_kind(t.kind),
+            range: lines.range((t.start, t.end)),
+        })
+        .collect();
+
+    SyntaxTree {
+        root: stack.pop().unwrap().node,
+        tokens,
+    }
+}
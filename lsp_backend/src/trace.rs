@@ -0,0 +1,26 @@
+//! `$/setTrace` 和 `$/logTrace`：在编辑器的 LSP 跟踪输出中查看服务器的处理过程
+
+use tower_lsp::lsp_types::notification::LogTrace;
+use tower_lsp::lsp_types::{LogTraceParams, SetTraceParams, TraceValue};
+
+use crate::Backend;
+
+impl Backend {
+    /// `$/setTrace`：客户端修改跟踪级别
+    pub async fn set_trace(&self, params: SetTraceParams) {
+        *self.trace.write().await = params.value;
+    }
+
+    /// 按当前跟踪级别发送 `$/logTrace`；`verbose` 只在 `verbose` 级别下计算和发送
+    pub(crate) async fn log_trace(&self, message: String, verbose: impl FnOnce() -> String) {
+        let verbose = match *self.trace.read().await {
+            TraceValue::Off => return,
+            TraceValue::Messages => None,
+            TraceValue::Verbose => Some(verbose()),
+        };
+
+        self.client
+            .send_notification::<LogTrace>(LogTraceParams { message, verbose })
+            .await;
+    }
+}
@@ -0,0 +1,91 @@
+//! 对上游类型表的只读查询
+
+use std::sync::{Arc, Mutex};
+
+use ant_type_checker::table::TypeTable;
+use tower_lsp::lsp_types::Url;
+
+use crate::Backend;
+use crate::cache::content_hash;
+use crate::document::Document;
+
+impl Backend {
+    pub(crate) fn builtins(&self) -> &TypeTable {
+        self.builtins.get_or_init(|| TypeTable::new().init())
+    }
+}
+
+/// 语言自带的类型名
+pub(crate) const BUILTIN_TYPES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+    "f64", "bool", "str", "String",
+];
+
+/// 内置名字（初始类型表中已有的）的类型，按类型检查器的格式显示
+pub(crate) fn builtin_type(builtins: &TypeTable, name: &str) -> Option<String> {
+    builtins.var_map.get(name).map(|ty| ty.to_string())
+}
+
+/// 两次分析之间类型表的变化，名字按字典序排列
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// 两边都有但类型不同的名字
+    pub changed: Vec<String>,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// `new` 相对 `old` 的变化，类型按显示出的文本比较
+pub(crate) fn diff(old: &TypeTable, new: &TypeTable) -> TableDiff {
+    let mut out = TableDiff::default();
+
+    for (name, ty) in &new.var_map {
+        match old.var_map.get(name) {
+            None => out.added.push(name.to_string()),
+            Some(prev) if prev.to_string() != ty.to_string() => out.changed.push(name.to_string()),
+            Some(_) => {}
+        }
+    }
+    out.removed = old
+        .var_map
+        .keys()
+        .filter(|name| !new.var_map.contains_key(*name))
+        .map(|name| name.to_string())
+        .collect();
+
+    out.added.sort();
+    out.removed.sort();
+    out.changed.sort();
+    out
+}
+
+impl Backend {
+    /// 记录文档内容为 `text` 时的类型表，与上一次的（首次分析时与内置作用域）比较得到变化
+    pub(crate) async fn update_type_table(&self, uri: &Url, text: &str, table: TypeTable) {
+        let mut tables = self.type_tables.write().await;
+
+        let changes = match tables.get(uri) {
+            Some((old, ..)) => diff(old, &table),
+            None => diff(self.builtins(), &table),
+        };
+        tables.insert(uri.clone(), (table, changes, content_hash(text)));
+    }
+
+    /// 文档内容与上次分析时相同时，上次分析得到的类型表
+    pub(crate) async fn current_type_table(
+        &self,
+        uri: &Url,
+        doc: &Document,
+    ) -> Option<Arc<Mutex<TypeTable>>> {
+        let hash = content_hash(&doc.text);
+        let tables = self.type_tables.read().await;
+        let (table, ..) = tables.get(uri).filter(|(.., it)| *it == hash)?;
+        Some(Arc::new(Mutex::new(table.clone())))
+    }
+}
@@ -4,6 +4,13 @@ pub(crate) trait UTF16Len {
     fn utf16_len(&self) -> usize;
 }
 
+impl UTF16Len for str {
+    fn utf16_len(&self) -> usize
+    {
+        self.encode_utf16().count()
+    }
+}
+
 impl UTF16Len for String {
     fn utf16_len(&self) -> usize
     {
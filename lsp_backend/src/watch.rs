@@ -0,0 +1,162 @@
+//! 磁盘上的文件变化（git checkout、外部工具生成等）
+
+use tower_lsp::lsp_types::*;
+
+use crate::config::CONFIG_FILE;
+use crate::workspace::SOURCE_EXT;
+use crate::{Backend, diagnose};
+
+impl Backend {
+    /// 客户端支持动态注册时，请求监听工作区内的源文件
+    pub(crate) async fn register_file_watchers(&self) {
+        let dynamic = self
+            .client_capabilities
+            .read()
+            .await
+            .workspace
+            .as_ref()
+            .and_then(|it| it.did_change_watched_files.as_ref())
+            .and_then(|it| it.dynamic_registration)
+            .unwrap_or(false);
+        if !dynamic {
+            return;
+        }
+
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![
+                FileSystemWatcher {
+                    glob_pattern: GlobPattern::String(format!("**/*.{SOURCE_EXT}")),
+                    kind: None,
+                },
+                FileSystemWatcher {
+                    glob_pattern: GlobPattern::String(format!("**/{CONFIG_FILE}")),
+                    kind: None,
+                },
+            ],
+        };
+        let registration = Registration {
+            id: "typedAnt/watchedFiles".into(),
+            method: "workspace/didChangeWatchedFiles".into(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(MessageType::WARNING, format!("cannot watch files: {err}"))
+                .await;
+        }
+    }
+
+    pub(crate) async fn on_watched_files(&self, changes: Vec<FileEvent>) {
+        let mut config_changed = false;
+
+        for change in changes {
+            let Ok(path) = change.uri.to_file_path() else {
+                continue;
+            };
+            if path.file_name().is_some_and(|name| name == CONFIG_FILE) {
+                config_changed = true;
+                continue;
+            }
+            if path.extension().is_none_or(|ext| ext != SOURCE_EXT) {
+                continue;
+            }
+
+            // 编辑器中打开的文件以编辑器内容为准
+            let open = self.documents.read().await.contains_key(&change.uri);
+
+            let closed_files = self
+                .settings
+                .read()
+                .await
+                .workspace
+                .diagnostics
+                .closed_files;
+
+            if change.typ == FileChangeType::DELETED {
+                if !open {
+                    self.workspace.write().await.remove_file(&change.uri);
+                    if self
+                        .closed_diagnostics
+                        .write()
+                        .await
+                        .remove(&change.uri)
+                        .is_some()
+                    {
+                        self.publish(&change.uri, &[], None).await;
+                    }
+                }
+            } else if !open {
+                // 读取失败（比如文件随即又被删除）时保留旧的索引
+                if let Ok(text) = std::fs::read_to_string(&path) {
+                    self.workspace
+                        .write()
+                        .await
+                        .index_file(change.uri.clone(), &text);
+                }
+                if closed_files {
+                    self.check_closed(&change.uri).await;
+                }
+            }
+
+            self.reanalyze_dependents(&change.uri).await;
+        }
+
+        if config_changed {
+            self.reload_config_file().await;
+        }
+    }
+
+    /// 重新分析直接或间接 import 了 `target` 的已打开文件
+    pub(crate) async fn reanalyze_dependents(&self, target: &Url) {
+        let dependents = self.workspace.read().await.dependents(target);
+
+        for uri in dependents {
+            self.analyses.write().await.forget(&uri);
+            let doc = self.documents.read().await.get(&uri).cloned();
+            if let Some(doc) = doc {
+                self.check_and_publish(&uri, &doc).await;
+            } else if self.closed_diagnostics.read().await.contains_key(&uri) {
+                self.check_closed(&uri).await;
+            }
+        }
+    }
+
+    /// 按磁盘上的内容分析未打开的文件并发布诊断（`workspace.diagnostics.closedFiles`）；
+    /// 文件读取失败或被排除时不再跟踪它，诊断暂停时只清除显示的诊断
+    pub(crate) async fn check_closed(&self, uri: &Url) {
+        let text = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok());
+        let excluded = !self.workspace.read().await.includes(uri);
+
+        let Some(text) = text.filter(|_| !excluded) else {
+            self.closed_diagnostics.write().await.remove(uri);
+            self.publish(uri, &[], None).await;
+            return;
+        };
+        if *self.diagnostics_paused.read().await {
+            self.closed_diagnostics
+                .write()
+                .await
+                .insert(uri.clone(), vec![]);
+            self.publish(uri, &[], None).await;
+            return;
+        }
+
+        let doc = self.new_document(text);
+        let settings = self.settings.read().await.clone();
+        let (diagnostics, _) = diagnose(&doc, uri, Some(self.builtins()), &settings, false);
+
+        // 分析期间文件被打开的话以打开的文档为准
+        if self.documents.read().await.contains_key(uri) {
+            return;
+        }
+        self.closed_diagnostics
+            .write()
+            .await
+            .insert(uri.clone(), diagnostics.clone());
+        self.publish(uri, &diagnostics, None).await;
+    }
+}
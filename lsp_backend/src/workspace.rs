@@ -0,0 +1,409 @@
+//! 工作区符号索引：记录每个文件导出的顶层符号
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+
+use tower_lsp::lsp_types::{PositionEncodingKind, Range, SymbolKind, Url};
+
+use crate::config::FileSettings;
+use crate::line_index::LineIndex;
+use crate::outline;
+use crate::symbols::lsp_kind;
+use crate::syntax;
+
+pub(crate) const SOURCE_EXT: &str = "ant";
+
+#[derive(Debug, Clone)]
+pub struct IndexedSymbol {
+    pub name: String,
+    pub range: Range,
+    pub kind: SymbolKind,
+    /// 写出的类型注解（函数为返回类型）
+    pub ty: Option<String>,
+    /// 文档注释标了 `@deprecated`
+    pub deprecated: bool,
+}
+
+/// 一个工作区目录中文件的索引
+#[derive(Debug, Default)]
+struct FolderIndex {
+    files: HashMap<Url, Vec<IndexedSymbol>>,
+    /// 每个文件 import 的文件
+    imports: HashMap<Url, Vec<Url>>,
+}
+
+impl FolderIndex {
+    fn remove(&mut self, uri: &Url) {
+        self.files.remove(uri);
+        self.imports.remove(uri);
+    }
+
+    /// 去掉 `root` 下的文件
+    fn forget_under(&mut self, root: &Path) {
+        let inside = |uri: &Url| uri.to_file_path().is_ok_and(|path| path.starts_with(root));
+        self.files.retain(|uri, _| !inside(uri));
+        self.imports.retain(|uri, _| !inside(uri));
+    }
+}
+
+/// 每个工作区目录（多根工作区中的每个 folder）各有独立的索引，符号不会跨目录可见。
+/// 文件按路径前缀归入最内层的目录；不在任何目录下的文件单独分析，看不到其他文件的符号
+#[derive(Debug, Default)]
+pub struct WorkspaceIndex {
+    folders: Vec<(PathBuf, FolderIndex)>,
+    /// 不属于任何工作区目录的文件
+    loose: FolderIndex,
+    /// 要分析（和索引）的文件
+    filter: FileSettings,
+    /// 符号范围的位置编码，None 为 UTF-16
+    encoding: Option<PositionEncodingKind>,
+    /// 不扫描工作区，只索引打开的文件和它们（直接或间接）import 的文件
+    lazy: bool,
+    /// 已经完成初次索引
+    ready: bool,
+}
+
+impl WorkspaceIndex {
+    pub fn set_roots(&mut self, roots: Vec<PathBuf>) {
+        self.folders = roots
+            .into_iter()
+            .map(|it| (it, Default::default()))
+            .collect();
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = &PathBuf> {
+        self.folders.iter().map(|(root, _)| root)
+    }
+
+    pub fn set_filter(&mut self, filter: FileSettings) {
+        self.filter = filter;
+    }
+
+    pub fn set_encoding(&mut self, encoding: PositionEncodingKind) {
+        self.encoding = Some(encoding);
+    }
+
+    pub fn set_lazy(&mut self, lazy: bool) {
+        self.lazy = lazy;
+    }
+
+    pub fn is_lazy(&self) -> bool {
+        self.lazy
+    }
+
+    pub fn ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn mark_ready(&mut self) {
+        self.ready = true;
+    }
+
+    /// 文件是否按配置需要分析：路径相对所在工作区目录，不在任何目录下时用完整路径
+    pub fn includes(&self, uri: &Url) -> bool {
+        let Ok(path) = uri.to_file_path() else {
+            return self.filter.includes(uri.path());
+        };
+        let relative = self
+            .root_of(uri)
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(&path);
+
+        let relative: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        self.filter.includes(&relative.join("/"))
+    }
+
+    /// 包含 `uri` 的最内层工作区目录
+    pub fn root_of(&self, uri: &Url) -> Option<&Path> {
+        self.folder_idx(uri)
+            .map(|idx| self.folders[idx].0.as_path())
+    }
+
+    fn folder_idx(&self, uri: &Url) -> Option<usize> {
+        let path = uri.to_file_path().ok()?;
+
+        self.folders
+            .iter()
+            .enumerate()
+            .filter(|(_, (root, _))| path.starts_with(root))
+            .max_by_key(|(_, (root, _))| root.components().count())
+            .map(|(idx, _)| idx)
+    }
+
+    fn folder(&self, uri: &Url) -> &FolderIndex {
+        match self.folder_idx(uri) {
+            Some(idx) => &self.folders[idx].1,
+            None => &self.loose,
+        }
+    }
+
+    /// 加入一个工作区目录，返回是否需要扫描其中的源文件（由调用方在不持有索引时读入）；
+    /// 目录已存在时不做任何事
+    pub fn add_root(&mut self, root: PathBuf) -> bool {
+        if self.folders.iter().any(|(it, _)| *it == root) {
+            return false;
+        }
+        self.folders.push((root.clone(), Default::default()));
+
+        // 原来归入外层目录或不属于任何目录的文件，由新目录重新扫描
+        for (_, folder) in &mut self.folders {
+            folder.forget_under(&root);
+        }
+        self.loose.forget_under(&root);
+
+        !self.lazy
+    }
+
+    /// 移除工作区目录及其索引，返回是否需要重新扫描这个目录：
+    /// 嵌套在其他目录中时，其中的文件改归外层目录。打开的文档由调用方重新索引
+    pub fn remove_root(&mut self, root: &Path) -> bool {
+        let before = self.folders.len();
+        self.folders.retain(|(it, _)| it != root);
+
+        let nested = self.folders.iter().any(|(it, _)| root.starts_with(it));
+        before != self.folders.len() && nested && !self.lazy
+    }
+
+    /// 清空索引，保留工作区目录
+    pub fn clear(&mut self) {
+        for (_, folder) in &mut self.folders {
+            *folder = Default::default();
+        }
+        self.loose = Default::default();
+    }
+
+    /// 索引目录下的所有源文件
+    pub fn scan(&mut self, root: &Path) {
+        self.index_sources(read_sources(root));
+    }
+
+    /// 索引 `read_sources` 读入的文件
+    pub fn index_sources(&mut self, files: Vec<(Url, String)>) {
+        for (uri, text) in files {
+            if self.includes(&uri) {
+                self.index_file(uri, &text);
+            }
+        }
+    }
+
+    /// 按配置不需要分析的文件不进入索引
+    pub fn index_file(&mut self, uri: Url, text: &str) {
+        if !self.includes(&uri) {
+            self.remove_file(&uri);
+            return;
+        }
+
+        let tokens = syntax::scan(text);
+        let outline = outline::outline(text, &tokens);
+        let encoding = self.encoding.clone().unwrap_or(PositionEncodingKind::UTF16);
+        let lines = LineIndex::with_encoding(text, &encoding);
+
+        let symbols = outline
+            .top_level()
+            .map(|s| IndexedSymbol {
+                name: s.name.clone(),
+                range: lines.range(s.name_span),
+                kind: lsp_kind(s.kind),
+                ty: s.ty.clone(),
+                deprecated: s.is_deprecated(),
+            })
+            .collect();
+
+        let imports = match uri.to_file_path() {
+            Ok(path) => outline
+                .imports
+                .iter()
+                .filter_map(|it| Url::from_file_path(resolve_import(&path, &it.path)).ok())
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        let folder = match self.folder_idx(&uri) {
+            Some(idx) => &mut self.folders[idx].1,
+            None => &mut self.loose,
+        };
+        folder.imports.insert(uri.clone(), imports.clone());
+        folder.files.insert(uri.clone(), symbols);
+
+        // 按需索引时，第一次被 import 的文件从磁盘读入；先插入自身再递归，循环 import 不会死循环
+        if self.lazy {
+            for import in imports {
+                if self.folder(&import).files.contains_key(&import) {
+                    continue;
+                }
+                let text = import
+                    .to_file_path()
+                    .ok()
+                    .and_then(|path| std::fs::read_to_string(path).ok());
+                if let Some(text) = text {
+                    self.index_file(import, &text);
+                }
+            }
+        }
+    }
+
+    pub fn remove_file(&mut self, uri: &Url) {
+        for (_, folder) in &mut self.folders {
+            folder.remove(uri);
+        }
+        self.loose.remove(uri);
+    }
+
+    /// 文件导出的顶层符号，未索引的文件为空
+    pub fn symbols(&self, uri: &Url) -> &[IndexedSymbol] {
+        self.folder(uri)
+            .files
+            .get(uri)
+            .map_or(&[], |it| it.as_slice())
+    }
+
+    /// `uri` 直接 import 的文件
+    pub fn imports_of(&self, uri: &Url) -> &[Url] {
+        self.folder(uri)
+            .imports
+            .get(uri)
+            .map_or(&[], |it| it.as_slice())
+    }
+
+    /// 同一目录中直接或间接 import 了 `uri` 的文件（不含自身），循环 import 不会导致死循环
+    pub fn dependents(&self, uri: &Url) -> Vec<Url> {
+        let folder = self.folder(uri);
+        let mut visited = HashSet::from([uri.clone()]);
+        let mut queue = VecDeque::from([uri.clone()]);
+        let mut found = vec![];
+
+        while let Some(current) = queue.pop_front() {
+            for (file, imports) in &folder.imports {
+                if imports.contains(&current) && visited.insert(file.clone()) {
+                    found.push(file.clone());
+                    queue.push_back(file.clone());
+                }
+            }
+        }
+
+        found.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        found
+    }
+
+    /// `from` 所在目录中所有文件（包括 `from` 自身）导出的符号，按文件排序以保证稳定；
+    /// 不在任何目录下的文件看不到其他文件的符号
+    pub fn exported(&self, from: &Url) -> Vec<(&Url, &IndexedSymbol)> {
+        let Some(idx) = self.folder_idx(from) else {
+            return vec![];
+        };
+
+        let mut found: Vec<_> = self.folders[idx]
+            .1
+            .files
+            .iter()
+            .flat_map(|(uri, symbols)| symbols.iter().map(move |s| (uri, s)))
+            .collect();
+
+        found.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        found
+    }
+
+    /// 所有已索引的文件（各工作区目录和不属于任何目录的），按路径排序
+    pub fn all_files(&self) -> Vec<Url> {
+        let mut found: Vec<_> = self
+            .folders
+            .iter()
+            .map(|(_, folder)| folder)
+            .chain([&self.loose])
+            .flat_map(|folder| folder.files.keys().cloned())
+            .collect();
+
+        found.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        found
+    }
+
+    /// 在 `from` 所在的目录中按名字查找导出符号
+    pub fn find(&self, from: &Url, name: &str) -> Vec<(&Url, &IndexedSymbol)> {
+        self.exported(from)
+            .into_iter()
+            .filter(|(_, s)| s.name == name)
+            .collect()
+    }
+}
+
+/// 读入目录下的所有源文件。目录很大时耗时较长，不应持有索引的锁调用
+pub(crate) fn read_sources(root: &Path) -> Vec<(Url, String)> {
+    let mut files = Vec::new();
+    collect_sources(root, &mut files);
+
+    files
+        .into_iter()
+        .filter_map(|path| {
+            let uri = Url::from_file_path(&path).ok()?;
+            let text = std::fs::read_to_string(&path).ok()?;
+            Some((uri, text))
+        })
+        .collect()
+}
+
+/// 不跟随指向目录的符号链接，链接成环时不会无限递归
+pub(crate) fn collect_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        if file_type.is_dir() {
+            if !hidden && entry.file_name() != "target" {
+                collect_sources(&path, out);
+            }
+        } else if path.extension().is_some_and(|ext| ext == SOURCE_EXT) {
+            out.push(path);
+        }
+    }
+}
+
+/// `from` 所在目录到 `to` 的相对路径（用 `/` 分隔），用于生成 import 语句
+pub(crate) fn relative_import_path(from: &Path, to: &Path) -> String {
+    let base: Vec<_> = from.parent().map_or(vec![], |p| p.components().collect());
+    let target: Vec<_> = to.components().collect();
+
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec![];
+    if common == base.len() {
+        parts.push(".".into());
+    }
+    parts.extend(std::iter::repeat_n("..".to_string(), base.len() - common));
+    parts.extend(
+        target[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+
+    parts.join("/")
+}
+
+/// `from` 中 `import "<path>"` 指向的文件，按字面消去 `.` 和 `..`
+pub(crate) fn resolve_import(from: &Path, import: &str) -> PathBuf {
+    let joined = from.parent().unwrap_or(Path::new("")).join(import);
+
+    let mut out = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
@@ -0,0 +1,93 @@
+//! 工作区符号搜索（`workspace/symbol`），客户端给了 partial result token 时分批发送结果
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::notification::Notification;
+use tower_lsp::lsp_types::*;
+
+use crate::Backend;
+
+/// 每批发送的符号个数
+const CHUNK_SIZE: usize = 100;
+
+/// 通过 `$/progress` 发送的部分结果；`ProgressParamsValue` 只有 work done 一种，这里单独定义
+#[derive(Debug, Serialize, Deserialize)]
+struct SymbolChunk {
+    token: ProgressToken,
+    value: Vec<SymbolInformation>,
+}
+
+enum SymbolProgress {}
+
+impl Notification for SymbolProgress {
+    type Params = SymbolChunk;
+    const METHOD: &'static str = "$/progress";
+}
+
+/// `query` 中的字符按顺序出现在 `name` 中，不区分大小写；空查询匹配所有名字
+fn fuzzy_match(name: &str, query: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| name.any(|c| c == q))
+}
+
+impl Backend {
+    /// 在工作区索引中搜索导出的符号。有 partial result token 时每凑够一批就通过 `$/progress`
+    /// 发送，最终响应为空。逐个文件搜索，文件之间释放索引的锁并让出执行：
+    /// 客户端取消请求时 tower-lsp 丢弃这个 future，搜索在下一个文件之前停止
+    #[allow(deprecated)]
+    pub(crate) async fn workspace_symbols(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Vec<SymbolInformation> {
+        let token = params.partial_result_params.partial_result_token;
+        let files = self.workspace.read().await.all_files();
+        let mut found: Vec<SymbolInformation> = vec![];
+
+        for uri in files {
+            tokio::task::yield_now().await;
+            {
+                let workspace = self.workspace.read().await;
+                let matches = workspace
+                    .symbols(&uri)
+                    .iter()
+                    .filter(|s| fuzzy_match(&s.name, &params.query))
+                    .map(|s| SymbolInformation {
+                        name: s.name.clone(),
+                        kind: s.kind,
+                        tags: s.deprecated.then(|| vec![SymbolTag::DEPRECATED]),
+                        deprecated: None,
+                        location: Location::new(uri.clone(), s.range),
+                        container_name: None,
+                    });
+                found.extend(matches);
+            }
+
+            if let Some(token) = &token {
+                while found.len() >= CHUNK_SIZE {
+                    let chunk = found.drain(..CHUNK_SIZE).collect();
+                    self.send_symbols(token, chunk).await;
+                }
+            }
+        }
+
+        match &token {
+            Some(token) => {
+                if !found.is_empty() {
+                    self.send_symbols(token, found).await;
+                }
+                vec![]
+            }
+            None => found,
+        }
+    }
+
+    async fn send_symbols(&self, token: &ProgressToken, value: Vec<SymbolInformation>) {
+        let chunk = SymbolChunk {
+            token: token.clone(),
+            value,
+        };
+        self.client.send_notification::<SymbolProgress>(chunk).await;
+    }
+}
@@ -0,0 +1,347 @@
+use lsp_backend::check_file;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position};
+
+/// 把源码写到临时文件中再分析
+fn check(name: &str, text: &str) -> Vec<Diagnostic> {
+    let path = std::env::temp_dir().join(format!("typed_ant_lsp_{name}.ant"));
+    std::fs::write(&path, text).unwrap();
+    let diagnostics = check_file(&path);
+    let _ = std::fs::remove_file(&path);
+    diagnostics
+}
+
+fn with_code<'a>(diagnostics: &'a [Diagnostic], code: &str) -> Vec<&'a Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|it| it.code == Some(NumberOrString::String(code.into())))
+        .collect()
+}
+
+#[test]
+fn duplicate_parameter() {
+    let text = "\
+func add(a: i64, b: i64) -> i64 {
+    return a + b;
+}
+
+func twice(x: i64, y: i64, x: i64) -> i64 {
+    return x + y;
+}
+";
+    let diagnostics = check("duplicate_parameter", text);
+    let found = with_code(&diagnostics, "duplicate-parameter");
+
+    assert_eq!(found.len(), 1, "{diagnostics:?}");
+    assert_eq!(found[0].range.start.line, 4);
+    assert_eq!(found[0].range.start.character, 27);
+
+    let related = found[0].related_information.as_ref().unwrap();
+    assert_eq!(related[0].location.range.start.character, 11);
+}
+
+#[test]
+fn incompatible_comparison() {
+    let text = "\
+struct Point { x: i64, y: i64 }
+
+func origin() -> Point {
+    return Point { x: 0, y: 0 };
+}
+
+let count: i64 = 3;
+let limit: i64 = 10;
+let ok = count < limit;
+let also_ok = count == 3;
+let bad = count == \"three\";
+let worse = origin() != 0;
+";
+    let diagnostics = check("incompatible_comparison", text);
+    let found = with_code(&diagnostics, "incompatible-comparison");
+
+    assert_eq!(found.len(), 2, "{diagnostics:?}");
+    assert_eq!(found[0].range.start.line, 10);
+    assert_eq!(found[0].range.start.character, 16);
+    assert_eq!(
+        found[0].message,
+        "cannot compare `i64` with `{string}` using `==`: the types are unrelated"
+    );
+    assert_eq!(found[1].range.start.line, 11);
+    assert!(found[1].message.contains("`Point` with `{number}`"));
+}
+
+#[test]
+fn todo_comments_are_off_by_default() {
+    let text = "\
+// TODO: handle negative numbers
+let x = 1;
+";
+    let diagnostics = check("todo_comment", text);
+    assert!(
+        with_code(&diagnostics, "todo-comment").is_empty(),
+        "{diagnostics:?}"
+    );
+}
+
+#[test]
+fn constant_conditions_are_off_by_default() {
+    let text = "\
+func f(x: i64) -> i64 {
+    if true {
+        return x;
+    } else {
+        return 0;
+    }
+}
+";
+    let diagnostics = check("constant_condition", text);
+    assert!(
+        with_code(&diagnostics, "constant-condition").is_empty(),
+        "{diagnostics:?}"
+    );
+}
+
+#[test]
+fn empty_function_body() {
+    let text = "\
+func one() -> i64 {
+    return 1;
+}
+
+func two() -> i64 {}
+";
+    let diagnostics = check("empty_function_body", text);
+    let found = with_code(&diagnostics, "empty-function-body");
+
+    assert_eq!(found.len(), 1, "{diagnostics:?}");
+    assert_eq!(found[0].range.start.line, 4);
+    assert_eq!(found[0].range.start.character, 18);
+    assert!(with_code(&diagnostics, "missing-return").is_empty());
+}
+
+#[test]
+fn infinite_loop_does_not_fall_through() {
+    let text = "\
+func f() -> i64 { while true { return 1; } }
+
+func g(n: i64) -> i64 {
+    while true {
+        if n > 0 {
+            break;
+        }
+    }
+}
+";
+    let diagnostics = check("infinite_loop", text);
+    let found = with_code(&diagnostics, "missing-return");
+
+    // 只有带 `break` 的循环之后会落到函数末尾
+    assert_eq!(found.len(), 1, "{diagnostics:?}");
+    assert_eq!(found[0].range.start.line, 8);
+}
+
+#[test]
+fn recursive_type() {
+    let text = "\
+struct Point { x: i64, y: i64 }
+struct Line { from: Point, to: Point }
+struct Node { value: i64, next: Node }
+struct A { b: B }
+struct B { a: A }
+";
+    let diagnostics = check("recursive_type", text);
+    let found = with_code(&diagnostics, "recursive-type");
+
+    assert_eq!(found.len(), 2, "{diagnostics:?}");
+    assert_eq!(found[0].range.start.line, 2);
+    assert_eq!(found[1].range.start.line, 3);
+    assert_eq!(found[1].related_information.as_ref().unwrap().len(), 2);
+}
+
+#[test]
+fn integer_overflow() {
+    let text = "\
+struct Pixel { r: u8, g: u8 }
+
+func shade(level: i8) -> u16 {
+    return 65536;
+}
+
+let max: i8 = 127;
+let too_big: i8 = 128;
+let min: i8 = -128;
+let too_small: i8 = -129;
+let unsigned: u8 = -1;
+let wide: i64 = 9223372036854775808;
+let p = Pixel { r: 255, g: 256 };
+let s = shade(-200);
+";
+    let diagnostics = check("integer_overflow", text);
+    let found = with_code(&diagnostics, "integer-overflow");
+
+    let lines: Vec<_> = found.iter().map(|it| it.range.start.line).collect();
+    assert_eq!(lines, [3, 7, 9, 10, 11, 12, 13], "{diagnostics:?}");
+    assert!(
+        found[1].message.contains("-128..=127"),
+        "{}",
+        found[1].message
+    );
+    assert_eq!(found[2].range.start.character, 20);
+}
+
+#[test]
+fn struct_literal_fields() {
+    let text = "\
+struct Point { x: i64, y: i64, label: str }
+
+let ok = Point { x: 1, y: 2, label: \"a\" };
+let typo = Point { x: 1, yy: 2, label: \"b\" };
+let short = Point { x: 1 };
+";
+    let diagnostics = check("struct_literal_fields", text);
+
+    let unknown = with_code(&diagnostics, "unknown-field");
+    assert_eq!(unknown.len(), 1, "{diagnostics:?}");
+    assert_eq!(unknown[0].range.start.line, 3);
+    assert_eq!(unknown[0].range.start.character, 25);
+    assert!(
+        unknown[0].message.contains("did you mean `y`"),
+        "{}",
+        unknown[0].message
+    );
+
+    let missing = with_code(&diagnostics, "missing-fields");
+    assert_eq!(missing.len(), 2, "{diagnostics:?}");
+    assert_eq!(missing[0].range.start.line, 3);
+    assert_eq!(
+        missing[1].message,
+        "missing fields `y`, `label` in `Point` literal"
+    );
+}
+
+#[test]
+fn return_type_mismatch() {
+    let text = "\
+func describe(n: i64) -> i64 {
+    if n < 0 {
+        return \"negative\";
+    }
+    if n == 0 {
+        return;
+    }
+    return n;
+}
+";
+    let diagnostics = check("return_type_mismatch", text);
+    let found = with_code(&diagnostics, "return-type-mismatch");
+
+    assert_eq!(found.len(), 2, "{diagnostics:?}");
+    assert_eq!(found[0].range.start, Position::new(2, 15));
+    assert!(
+        found[0].message.contains("`{string}`"),
+        "{}",
+        found[0].message
+    );
+    assert!(
+        found[1].message.contains("found `()`"),
+        "{}",
+        found[1].message
+    );
+
+    let related = found[0].related_information.as_ref().unwrap();
+    assert_eq!(related[0].location.range.start.character, 25);
+}
+
+#[test]
+fn not_callable() {
+    let text = "\
+func double(n: i64) -> i64 {
+    return n * 2;
+}
+
+let count: i64 = 3;
+let ok = double(count);
+let bad = count(1);
+";
+    let diagnostics = check("not_callable", text);
+    let found = with_code(&diagnostics, "not-callable");
+
+    assert_eq!(found.len(), 1, "{diagnostics:?}");
+    assert_eq!(
+        found[0].message,
+        "`count` is not callable; it has type `i64`"
+    );
+    assert_eq!(found[0].range.start, Position::new(6, 10));
+    assert_eq!(found[0].range.end, Position::new(6, 15));
+}
+
+#[test]
+fn assignment_in_condition() {
+    let text = "\
+func check(a: i64, b: i64) -> bool {
+    let mut same = false;
+    if (same = a == b) {
+        return true;
+    }
+    while a == b {
+        return false;
+    }
+    return same;
+}
+";
+    let diagnostics = check("assignment_in_condition", text);
+    let found = with_code(&diagnostics, "assignment-in-condition");
+
+    assert_eq!(found.len(), 1, "{diagnostics:?}");
+    assert_eq!(found[0].range.start, Position::new(2, 13));
+    assert_eq!(found[0].severity, Some(DiagnosticSeverity::WARNING));
+}
+
+#[test]
+fn unexpected_return_value() {
+    let text = "\
+func log(n: i64) {
+    if n < 0 {
+        return;
+    }
+    return n;
+}
+";
+    let diagnostics = check("unexpected_return_value", text);
+    let found = with_code(&diagnostics, "unexpected-return-value");
+
+    assert_eq!(found.len(), 1, "{diagnostics:?}");
+    assert_eq!(found[0].range.start, Position::new(4, 11));
+    assert!(
+        found[0].message.contains("of type `i64`"),
+        "{}",
+        found[0].message
+    );
+
+    let related = found[0].related_information.as_ref().unwrap();
+    assert_eq!(related[0].location.range.start, Position::new(0, 5));
+}
+
+#[test]
+fn no_such_field() {
+    let text = "\
+struct Point { x: i64, y: i64 }
+struct Line { start: Point, end: Point }
+
+func length(line: Line) -> i64 {
+    return line.end.x - line.stat.x + line.end.z;
+}
+";
+    let diagnostics = check("no_such_field", text);
+    let found = with_code(&diagnostics, "no-such-field");
+
+    assert_eq!(found.len(), 2, "{diagnostics:?}");
+    assert_eq!(
+        found[0].message,
+        "no field `stat` on type `Line`; did you mean `start`?"
+    );
+    assert_eq!(found[0].range.start, Position::new(4, 29));
+    assert_eq!(
+        found[1].message,
+        "no field `z` on type `Point`; did you mean `x`?"
+    );
+}
@@ -0,0 +1,35 @@
+use lsp_backend::Settings;
+use serde_json::json;
+
+#[test]
+fn invalid_setting_keeps_the_rest() {
+    let value = json!({
+        "typedAnt": {
+            "maxFileSizeBytes": "big",
+            "completion": { "minPrefixLength": 2, "callParens": "sometimes" },
+            "diagnostics": { "severity": { "todo-comment": "hint", "unused": "loud" } },
+        }
+    });
+    let (settings, errors) = Settings::parse(&value);
+
+    assert_eq!(
+        settings.max_file_size_bytes,
+        Settings::default().max_file_size_bytes
+    );
+    assert_eq!(settings.completion.min_prefix_length, 2);
+    assert_eq!(settings.diagnostics.severity.len(), 1);
+
+    let mut paths: Vec<_> = errors
+        .iter()
+        .map(|it| it.split(':').next().unwrap())
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "typedAnt.completion.callParens",
+            "typedAnt.diagnostics.severity.unused",
+            "typedAnt.maxFileSizeBytes",
+        ]
+    );
+}
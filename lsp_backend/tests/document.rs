@@ -0,0 +1,55 @@
+use lsp_backend::apply_change;
+use tower_lsp::lsp_types::{Position, PositionEncodingKind, Range, TextDocumentContentChangeEvent};
+
+fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: Some(Range::new(
+            Position::new(start.0, start.1),
+            Position::new(end.0, end.1),
+        )),
+        range_length: None,
+        text: text.into(),
+    }
+}
+
+#[test]
+fn full_text_replaces_everything() {
+    let change = TextDocumentContentChangeEvent {
+        range: None,
+        range_length: None,
+        text: "let y = 2;\n".into(),
+    };
+    let text = apply_change("let x = 1;\n", &change, &PositionEncodingKind::UTF16);
+    assert_eq!(text, "let y = 2;\n");
+}
+
+#[test]
+fn multi_line_edit() {
+    let text = "let a = 1;\nlet b = 2;\nlet c = 3;\n";
+    // 从第一行的 `1` 删到第三行的 `3` 之前
+    let change = edit((0, 8), (2, 8), "4");
+    let text = apply_change(text, &change, &PositionEncodingKind::UTF16);
+    assert_eq!(text, "let a = 43;\n");
+
+    let change = edit((1, 0), (1, 0), "let d = 5;\nlet e = 6;\n");
+    let text = apply_change(&text, &change, &PositionEncodingKind::UTF16);
+    assert_eq!(text, "let a = 43;\nlet d = 5;\nlet e = 6;\n");
+}
+
+#[test]
+fn multi_byte_edit() {
+    // 😀 占两个 UTF-16 码元、四个字节，é 占一个 UTF-16 码元、两个字节
+    let text = "let s = \"😀é\";\nlet t = 1;\n";
+
+    let change = edit((0, 11), (0, 12), "e");
+    let utf16 = apply_change(text, &change, &PositionEncodingKind::UTF16);
+    assert_eq!(utf16, "let s = \"😀e\";\nlet t = 1;\n");
+
+    let change = edit((0, 13), (0, 15), "e");
+    let utf8 = apply_change(text, &change, &PositionEncodingKind::UTF8);
+    assert_eq!(utf8, "let s = \"😀e\";\nlet t = 1;\n");
+
+    let change = edit((0, 9), (1, 0), "\";\n");
+    let joined = apply_change(text, &change, &PositionEncodingKind::UTF16);
+    assert_eq!(joined, "let s = \"\";\nlet t = 1;\n");
+}
@@ -0,0 +1,119 @@
+//! 按 `tests/fixtures/*.ant` 中的注释检查诊断
+//!
+//! `//~ LEVEL message` 表示这一行有一条该严重程度、消息包含 `message` 的诊断；
+//! `//~^` 指向上一行，每多一个 `^` 再往上一行。`message` 可以省略。
+//! 文件中的诊断必须与注释一一对应，多出或缺少的都算失败。
+
+use std::path::Path;
+
+use lsp_backend::{check_file, source_files};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+#[derive(Debug)]
+struct Expected {
+    /// 从 0 开始的行号
+    line: u32,
+    severity: DiagnosticSeverity,
+    message: String,
+}
+
+fn severity(level: &str) -> Option<DiagnosticSeverity> {
+    match level {
+        "ERROR" => Some(DiagnosticSeverity::ERROR),
+        "WARNING" => Some(DiagnosticSeverity::WARNING),
+        "INFO" => Some(DiagnosticSeverity::INFORMATION),
+        "HINT" => Some(DiagnosticSeverity::HINT),
+        _ => None,
+    }
+}
+
+fn annotations(path: &Path, text: &str) -> Vec<Expected> {
+    let mut out = vec![];
+
+    for (line, content) in text.lines().enumerate() {
+        let Some((_, rest)) = content.split_once("//~") else {
+            continue;
+        };
+        let up = rest.chars().take_while(|c| *c == '^').count();
+        let rest = rest[up..].trim();
+        let (level, message) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        let severity = severity(level)
+            .unwrap_or_else(|| panic!("{}:{}: unknown level `{level}`", path.display(), line + 1));
+        out.push(Expected {
+            line: (line - up) as u32,
+            severity,
+            message: message.trim().to_string(),
+        });
+    }
+
+    out
+}
+
+fn matches(expected: &Expected, diag: &Diagnostic) -> bool {
+    diag.range.start.line == expected.line
+        && diag.severity == Some(expected.severity)
+        && diag.message.contains(&expected.message)
+}
+
+/// 一个诊断只能对应一条注释
+fn compare(path: &Path, mut expected: Vec<Expected>, diagnostics: &[Diagnostic]) -> Vec<String> {
+    let mut errors = vec![];
+
+    for diag in diagnostics {
+        match expected.iter().position(|it| matches(it, diag)) {
+            Some(idx) => {
+                expected.remove(idx);
+            }
+            None => errors.push(format!(
+                "{}:{}: unexpected {:?}: {}",
+                path.display(),
+                diag.range.start.line + 1,
+                diag.severity,
+                diag.message
+            )),
+        }
+    }
+    for it in expected {
+        errors.push(format!(
+            "{}:{}: missing {:?}: {}",
+            path.display(),
+            it.line + 1,
+            it.severity,
+            it.message
+        ));
+    }
+
+    errors
+}
+
+#[test]
+fn fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let files = source_files(&dir);
+    assert!(!files.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut errors = vec![];
+    for path in &files {
+        let text = std::fs::read_to_string(path).unwrap();
+        errors.extend(compare(path, annotations(path, &text), &check_file(path)));
+    }
+
+    assert!(errors.is_empty(), "\n{}", errors.join("\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_directories_are_skipped() {
+    let dir = std::env::temp_dir().join(format!("typed-ant-sources-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.ant"), "").unwrap();
+    // 指回上层的链接成环
+    std::os::unix::fs::symlink(&dir, dir.join("src/loop")).unwrap();
+
+    let files = source_files(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(files, vec![dir.join("src/main.ant")]);
+}
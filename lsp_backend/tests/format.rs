@@ -0,0 +1,65 @@
+use lsp_backend::{FormatSettings, format_text};
+use tower_lsp::lsp_types::FormattingOptions;
+
+#[path = "../benches/fixtures/generated.rs"]
+mod generated;
+
+fn format(text: &str, max_width: usize) -> String {
+    let options = FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..Default::default()
+    };
+
+    format_text(text, &options, &FormatSettings { max_width }).expect("source should parse")
+}
+
+/// 去掉所有空白后比较，格式化不能改变 token
+fn tokens_of(text: &str) -> String {
+    text.split_whitespace().collect()
+}
+
+const LONG_CALL: &str = "\
+func add3(a: i64, b: i64, c: i64) -> i64 {
+return a + b + c;
+}
+
+let total = add3(add3(1111111, 2222222, 3333333), add3(4444444, 5555555, 6666666), 7777777);
+";
+
+#[test]
+fn wraps_long_argument_list() {
+    let formatted = format(LONG_CALL, 60);
+
+    assert!(
+        formatted.lines().all(|line| line.chars().count() <= 60),
+        "{formatted}"
+    );
+    assert!(formatted.contains("let total = add3(\n    add3(1111111, 2222222, 3333333),\n"));
+    assert_eq!(tokens_of(&formatted), tokens_of(LONG_CALL));
+}
+
+#[test]
+fn keeps_lines_within_width() {
+    let formatted = format(LONG_CALL, 100);
+
+    assert_eq!(formatted.lines().count(), LONG_CALL.lines().count());
+    assert_eq!(formatted.lines().last(), LONG_CALL.lines().last());
+}
+
+#[test]
+fn idempotent() {
+    let medium = generated::source(40);
+    let fixtures = [
+        LONG_CALL,
+        include_str!("../benches/fixtures/small.ant"),
+        &medium,
+    ];
+
+    for text in fixtures {
+        for max_width in [20, 40, 60, 100] {
+            let once = format(text, max_width);
+            assert_eq!(format(&once, max_width), once, "max_width = {max_width}");
+        }
+    }
+}
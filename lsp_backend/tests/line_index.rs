@@ -0,0 +1,51 @@
+use lsp_backend::LineIndex;
+use tower_lsp::lsp_types::{Position, PositionEncodingKind, Range};
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+    Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+}
+
+#[test]
+fn astral_char_before_token() {
+    let lines = LineIndex::new("let s = 😀 + x;");
+
+    // 😀 是一个字符，但占两个 UTF-16 码元
+    assert_eq!(lines.token_range(1, 13, "x"), range((0, 13), (0, 14)));
+}
+
+#[test]
+fn astral_char_inside_token() {
+    let lines = LineIndex::new("let s = \"a😀b\";");
+
+    assert_eq!(lines.token_range(1, 9, "\"a😀b\""), range((0, 8), (0, 14)));
+}
+
+#[test]
+fn astral_char_before_and_inside_token() {
+    let lines = LineIndex::new("😀 \"😀\"\n");
+
+    assert_eq!(lines.token_range(1, 3, "\"😀\""), range((0, 3), (0, 7)));
+}
+
+#[test]
+fn multi_line_token_ends_on_last_line() {
+    let lines = LineIndex::new("x = \"😀\nb😀\";");
+
+    assert_eq!(
+        lines.token_range(1, 5, "\"😀\nb😀\""),
+        range((0, 4), (1, 4))
+    );
+}
+
+#[test]
+fn negotiated_encodings() {
+    let text = "let s = 😀 + x;";
+
+    let utf8 = LineIndex::with_encoding(text, &PositionEncodingKind::UTF8);
+    assert_eq!(utf8.token_range(1, 13, "x"), range((0, 15), (0, 16)));
+    assert_eq!(utf8.offset(Position::new(0, 15)), 15);
+
+    let utf32 = LineIndex::with_encoding(text, &PositionEncodingKind::UTF32);
+    assert_eq!(utf32.token_range(1, 13, "x"), range((0, 12), (0, 13)));
+    assert_eq!(utf32.offset(Position::new(0, 12)), 15);
+}
@@ -0,0 +1,61 @@
+use lsp_backend::render_diagnostic;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+
+fn diagnostic(start: (u32, u32), end: (u32, u32)) -> Diagnostic {
+    Diagnostic {
+        range: Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String("type-error".into())),
+        message: "mismatched types".into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn caret_under_range() {
+    let source = "let a = 1;\nlet b = a + \"x\";\n";
+    let rendered = render_diagnostic(&diagnostic((1, 12), (1, 15)), source, "main.ant", false);
+
+    assert_eq!(
+        rendered,
+        "\
+error[type-error]: mismatched types
+ --> main.ant:2:13
+  |
+2 | let b = a + \"x\";
+  |             ^^^
+"
+    );
+}
+
+#[test]
+fn caret_after_astral_char() {
+    // 😀 占两个 UTF-16 码元，但只占一个字符宽度
+    let source = "let s = \"😀\" + x;";
+    let rendered = render_diagnostic(&diagnostic((0, 15), (0, 16)), source, "main.ant", false);
+
+    assert!(
+        rendered.ends_with("1 | let s = \"😀\" + x;\n  |               ^\n"),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn multi_line_range() {
+    let source = "let s = \"a\nb\";\n";
+    let rendered = render_diagnostic(&diagnostic((0, 8), (1, 2)), source, "main.ant", false);
+
+    assert!(
+        rendered.ends_with("1 | let s = \"a\n  |         ^^\n2 | b\";\n  | ^^\n"),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn no_escape_codes_without_color() {
+    let rendered = render_diagnostic(&diagnostic((0, 0), (0, 3)), "let", "main.ant", false);
+    assert!(!rendered.contains('\x1b'));
+
+    let colored = render_diagnostic(&diagnostic((0, 0), (0, 3)), "let", "main.ant", true);
+    assert!(colored.contains('\x1b'));
+}
@@ -0,0 +1,43 @@
+use lsp_backend::{Document, visible_symbols_at};
+use tower_lsp::lsp_types::Position;
+
+const TEXT: &str = "\
+let x: i64 = 1;
+
+func f(n: i64) -> i64 {
+    let x: str = \"inner\";
+    if n > 0 {
+        let y = n;
+        return y;
+    }
+    return n;
+}
+";
+
+#[test]
+fn resolve_prefers_inner_scope() {
+    let doc = Document::new(TEXT.to_string());
+    let scopes = doc.scope_tree();
+
+    let inner = scopes.resolve(Position::new(5, 8), "x").unwrap();
+    assert_eq!(inner.ty.as_deref(), Some("str"));
+    assert_eq!(inner.name_range.start, Position::new(3, 8));
+
+    let outer = scopes.resolve(Position::new(0, 15), "x").unwrap();
+    assert_eq!(outer.ty.as_deref(), Some("i64"));
+
+    // `y` 只在 if 块里可见
+    assert!(scopes.resolve(Position::new(8, 4), "y").is_none());
+}
+
+#[test]
+fn visible_symbols_hide_shadowed() {
+    let visible = visible_symbols_at(TEXT, Position::new(6, 8));
+    let names: Vec<_> = visible.iter().map(|it| it.name.as_str()).collect();
+    assert_eq!(names.iter().filter(|it| **it == "x").count(), 1);
+    assert_eq!(names[0], "y");
+    assert!(names.contains(&"n") && names.contains(&"f"));
+
+    let doc = Document::new(TEXT.to_string());
+    assert_eq!(doc.scope_tree().scopes_at(Position::new(6, 8)).len(), 3);
+}
@@ -0,0 +1,32 @@
+use lsp_backend::Document;
+use tower_lsp::lsp_types::DocumentSymbol;
+
+const TEXT: &str = "\
+struct Zone { width: i64, area: i64 }
+
+func main() {
+    let local = 1;
+}
+
+let count = 0;
+
+func build(size: i64, anchor: i64) -> i64 {
+    return size;
+}
+";
+
+fn names(symbols: &[DocumentSymbol]) -> Vec<&str> {
+    symbols.iter().map(|it| it.name.as_str()).collect()
+}
+
+#[test]
+fn document_symbols_follow_declaration_order() {
+    let symbols = Document::new(TEXT.to_string()).document_symbols();
+    assert_eq!(names(&symbols), ["Zone", "main", "count", "build"]);
+
+    let children = |idx: usize| names(symbols[idx].children.as_deref().unwrap_or_default());
+    assert_eq!(children(0), ["width", "area"]);
+    assert_eq!(children(3), ["size", "anchor"]);
+    // 局部变量不出现在大纲中
+    assert!(symbols[1].children.is_none());
+}
@@ -0,0 +1,100 @@
+//! 命令行模式：不启动 LSP 服务器，检查整个目录后退出，供 CI 使用
+
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use lsp_backend::{check_file, glob_matches, render_diagnostic, source_files};
+use serde_json::{Value, json};
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+const USAGE: &str = "usage: typed_ant_lsp check <dir> [--json] [--exclude <glob>]...";
+
+struct Options {
+    dir: PathBuf,
+    json: bool,
+    /// 相对于 `dir` 的路径匹配其中任一模式时跳过
+    exclude: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut dir = None;
+    let mut json = false;
+    let mut exclude = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--exclude" => match args.next() {
+                Some(glob) => exclude.push(glob.clone()),
+                None => return Err("missing pattern after --exclude".into()),
+            },
+            flag if flag.starts_with("--") => return Err(format!("unknown option: {flag}")),
+            path if dir.is_none() => dir = Some(PathBuf::from(path)),
+            path => return Err(format!("unexpected argument: {path}")),
+        }
+    }
+
+    Ok(Options {
+        dir: dir.ok_or("missing directory")?,
+        json,
+        exclude,
+    })
+}
+
+/// `check <dir>`：返回进程退出码，有错误时为 1，参数不对时为 2
+pub fn check(args: &[String]) -> i32 {
+    let options = match parse_args(args) {
+        Ok(it) => it,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return 2;
+        }
+    };
+    if !options.dir.is_dir() {
+        eprintln!("not a directory: {}", options.dir.display());
+        return 2;
+    }
+
+    let files: Vec<PathBuf> = source_files(&options.dir)
+        .into_iter()
+        .filter(|path| {
+            let relative = path.strip_prefix(&options.dir).unwrap_or(path);
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            !options.exclude.iter().any(|it| glob_matches(it, &relative))
+        })
+        .collect();
+
+    let mut errors = 0;
+    let mut report = vec![];
+    let color = std::io::stdout().is_terminal();
+
+    for path in &files {
+        let diagnostics = check_file(path);
+        errors += diagnostics
+            .iter()
+            .filter(|it| matches!(it.severity, Some(DiagnosticSeverity::ERROR) | None))
+            .count();
+
+        if options.json {
+            report.push(json!({
+                "file": path.display().to_string(),
+                "diagnostics": diagnostics,
+            }));
+        } else {
+            let source = std::fs::read_to_string(path).unwrap_or_default();
+            let name = path.display().to_string();
+            for diag in &diagnostics {
+                println!("{}", render_diagnostic(diag, &source, &name, color));
+            }
+        }
+    }
+
+    if options.json {
+        println!("{:#}", Value::Array(report));
+    } else {
+        eprintln!("checked {} files: {errors} errors", files.len());
+    }
+
+    i32::from(errors > 0)
+}
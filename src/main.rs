@@ -1,20 +1,26 @@
-use std::collections::HashMap;
+mod cli;
 
 use lsp_backend::Backend;
-use tokio::sync::RwLock;
 use tower_lsp::{LspService, Server};
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|it| it == "check") {
+        std::process::exit(cli::check(&args[1..]));
+    }
+
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend {
-        client,
-        documents: RwLock::new(HashMap::new()),
-    });
+    let (service, socket) = LspService::build(Backend::new)
+        .custom_method("typedAnt/analyzeFile", Backend::analyze_file)
+        .custom_method("typedAnt/formatText", Backend::format_text)
+        .custom_method("typedAnt/ast", Backend::syntax_tree)
+        .custom_method("$/setTrace", Backend::set_trace)
+        .finish();
 
     Server::new(stdin, stdout, socket)
         .serve(service)
         .await;
-}
\ No newline at end of file
+}